@@ -0,0 +1,14 @@
+#!/usr/bin/env bash
+
+set -euo pipefail
+
+name="$1"
+
+cargo rustc --release \
+  --package "$name" \
+  --lib \
+  --crate-type cdylib \
+  --features drumlogue_unit \
+  --target armv7-unknown-linux-gnueabihf
+
+cp "target/armv7-unknown-linux-gnueabihf/release/lib${name}.so" "$name/${name}.drmlgunit"
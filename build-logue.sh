@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+
+set -euo pipefail
+
+name="$1"
+
+cargo build --release \
+  --package "$name" \
+  --features logue_plugin \
+  --bin "${name}_logue" \
+  --target thumbv7em-none-eabihf
+
+cargo run --release --quiet --package logue_sdk_build --features emulator --bin logue_pack -- \
+  "target/thumbv7em-none-eabihf/release/${name}_logue" \
+  -o "$name" "${@:2}"
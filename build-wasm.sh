@@ -13,4 +13,7 @@ cargo build --release \
 
 cp "target/wasm32-unknown-unknown/release/${name}_wasm.wasm" "$name/$name.wasm"
 
+cp "target/wasm32-unknown-unknown/release/${name}.manifest.json" "$name/manifest.json"
+
 sed -e "s/@MODULE_NAME@/$name/g" < test-template.html > "$name/test.html"
+sed -e "s/@MODULE_NAME@/$name/g" < worklet-template.js > "$name/worklet.js"
@@ -18,6 +18,7 @@
         };
 
         armBinutils = pkgs.pkgsCross.arm-embedded.buildPackages.binutils-unwrapped;
+        armLinuxCC = pkgs.pkgsCross.armv7l-hf-multiplatform.buildPackages.gcc;
 
         minimodem = pkgs.callPackage ./minimodem {};
       in
@@ -30,9 +31,11 @@
               targets = [
                 "thumbv7em-none-eabihf"
                 "wasm32-unknown-unknown"
+                "armv7-unknown-linux-gnueabihf"
               ];
             })
             armBinutils
+            armLinuxCC
             rustfmt
             rust-analyzer
             pre-commit
@@ -47,7 +50,7 @@
             minimodem
           ];
 
-          HOST_OBJCOPY = "${armBinutils}/bin/arm-none-eabi-objcopy";
+          CARGO_TARGET_ARMV7_UNKNOWN_LINUX_GNUEABIHF_LINKER = "${armLinuxCC}/bin/armv7l-unknown-linux-gnueabihf-gcc";
         };
       });
 }
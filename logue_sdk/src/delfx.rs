@@ -0,0 +1,12 @@
+mod logue_interface;
+
+crate::sdram_fx::sdram_fx! {
+    magic: b"UDFX",
+    param: DelFxParam,
+    hook_table: UserDelFxHookTable,
+    /// A delay effect. Audio is processed in place, as interleaved stereo.
+    ///
+    /// Delay lines should be allocated from `sdram` in `init`, and kept in the
+    /// returned state, such as in a `dsp::StereoDelayLine`.
+    trait UserDelFx;
+}
@@ -0,0 +1,6 @@
+#[macro_export]
+macro_rules! user_delfx_hooks {
+    ($fx:ty) => {
+        $crate::__sdram_fx_hooks!(delfx, UserDelFxHookTable, UserDelFx, $fx);
+    };
+}
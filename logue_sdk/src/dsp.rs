@@ -1,3 +1,9 @@
+mod biquad;
+mod delay;
+
+pub use biquad::{Biquad, BiquadCoeffs, OnePole};
+pub use delay::{DelayLine, Frame, StereoDelayLine};
+
 #[inline(always)]
 pub fn f32_to_q31(x: f32) -> i32 {
     unsafe { (x * 0x7FFFFFBF as f32).to_int_unchecked() }
@@ -20,6 +26,16 @@ pub fn linintf(fr: f32, x0: f32, x1: f32) -> f32 {
     x0 + fr * (x1 - x0)
 }
 
+/// Catmull-Rom interpolation from `x0` to `x1`, using the points either side
+/// for the slopes.
+#[inline(always)]
+pub fn cubicintf(fr: f32, xm1: f32, x0: f32, x1: f32, x2: f32) -> f32 {
+    let c1 = 0.5 * (x1 - xm1);
+    let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+    let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+    ((c3 * fr + c2) * fr + c1) * fr + x0
+}
+
 #[inline(always)]
 pub fn si_roundf(x: f32) -> f32 {
     unsafe { (x + si_copysign(0.5, x)).to_int_unchecked::<i32>() as f32 }
@@ -35,3 +51,109 @@ pub fn si_copysign(x: f32, y: f32) -> f32 {
 
     f32::from_bits(xi)
 }
+
+/// `x` clipped to \[-1, 1\], then softened by a cubic: `x - c·x³`. A `c` of
+/// 1/3 gives a curve that flattens out at the clip points.
+#[inline(always)]
+pub fn osc_softclipf(c: f32, x: f32) -> f32 {
+    let x = x.clamp(-1.0, 1.0);
+    x - c * (x * x * x)
+}
+
+/// Cubic saturation, from the firmware's table. Reaches full scale at ±1 and
+/// clips beyond.
+#[inline(always)]
+pub fn osc_sat_cubicf(x: f32) -> f32 {
+    Waveshaper::cubic().shape(x)
+}
+
+/// Schetzen saturation, from the firmware's table. Reaches 0.75 at ±1 and
+/// clips beyond.
+#[inline(always)]
+pub fn osc_sat_schetzenf(x: f32) -> f32 {
+    Waveshaper::schetzen().shape(x)
+}
+
+/// A transfer curve in a table, for saturation and other waveshaping. The
+/// table holds the curve for inputs evenly spaced over \[0, 1\], and negative
+/// inputs are shaped like their magnitude with the sign put back. Inputs are
+/// scaled by the drive, then clipped to ±1.
+#[derive(Clone, Copy)]
+pub struct Waveshaper<'a> {
+    lut: &'a [f32],
+    drive: f32,
+}
+
+impl<'a> Waveshaper<'a> {
+    /// A shaper over `lut`, which needs at least two points.
+    #[inline(always)]
+    pub const fn new(lut: &'a [f32]) -> Self {
+        assert!(lut.len() >= 2, "a waveshaper table needs two points");
+        Self { lut, drive: 1.0 }
+    }
+
+    pub fn with_drive(mut self, drive: f32) -> Self {
+        self.drive = drive;
+        self
+    }
+
+    pub fn set_drive(&mut self, drive: f32) {
+        self.drive = drive;
+    }
+
+    #[inline(always)]
+    pub fn shape(&self, x: f32) -> f32 {
+        let last = self.lut.len() - 1;
+        let xf = (x * self.drive).abs().min(1.0) * last as f32;
+        let xi = (xf as usize).min(last - 1);
+        let y = linintf(xf - xi as f32, self.lut[xi], self.lut[xi + 1]);
+        si_copysign(y, x)
+    }
+
+    pub fn shape_buf(&self, buf: &mut [f32]) {
+        for x in buf {
+            *x = self.shape(*x);
+        }
+    }
+}
+
+impl Waveshaper<'static> {
+    /// The firmware's cubic saturation curve.
+    #[inline(always)]
+    pub fn cubic() -> Self {
+        Self::new(unsafe { &crate::oscapi::cubicsat_lut_f })
+    }
+
+    /// The firmware's Schetzen saturation curve.
+    #[inline(always)]
+    pub fn schetzen() -> Self {
+        Self::new(unsafe { &crate::oscapi::schetzen_lut_f })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waveshaper_is_odd_and_clips() {
+        let shaper = Waveshaper::new(&[0.0, 0.5, 0.75, 1.0, 1.0]);
+        assert_eq!(shaper.shape(0.125), 0.25);
+        assert_eq!(shaper.shape(-0.125), -0.25);
+        assert_eq!(shaper.shape(4.0), 1.0);
+        assert_eq!(shaper.shape(-4.0), -1.0);
+        assert_eq!(shaper.with_drive(2.0).shape(-0.25), -0.75);
+    }
+
+    #[test]
+    #[cfg(feature = "internal_luts")]
+    fn cubic_saturation() {
+        for i in -100..=100 {
+            let x = i as f32 / 100.0;
+            let expected = 1.5 * x - 0.5 * x * x * x;
+            assert!((osc_sat_cubicf(x) - expected).abs() < 1e-4, "{x}");
+        }
+        assert_eq!(osc_sat_cubicf(2.0), 1.0);
+        assert_eq!(osc_sat_schetzenf(-2.0), -0.75);
+    }
+}
@@ -0,0 +1,334 @@
+//! Biquad filters, after the SDK's `biquad.hpp`. Cutoffs are in Hz at the
+//! firmware's sample rate, prewarped with `osc_tanpif`.
+
+use crate::math::{fastpow2f, osc_tanpif};
+use crate::oscapi::SAMPLERATE_RECIPF;
+
+/// log2(10) / 40, to turn a gain in dB into the square root of its amplitude.
+const DB_TO_LOG2_SQRT: f32 = 0.083_048_05;
+
+/// tan(π·fc/fs), the bilinear transform's prewarped cutoff.
+#[inline(always)]
+fn prewarp(fc: f32) -> f32 {
+    osc_tanpif(fc * SAMPLERATE_RECIPF)
+}
+
+/// Coefficients of a second order section, normalised so the output's own
+/// coefficient is 1. Feedback coefficients are subtracted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadCoeffs {
+    pub ff0: f32,
+    pub ff1: f32,
+    pub ff2: f32,
+    pub fb1: f32,
+    pub fb2: f32,
+}
+
+impl BiquadCoeffs {
+    /// Passes the input through unchanged.
+    pub const THRU: Self = Self {
+        ff0: 1.0,
+        ff1: 0.0,
+        ff2: 0.0,
+        fb1: 0.0,
+        fb2: 0.0,
+    };
+
+    /// Divide through by `a0`, the coefficient of the output sample.
+    #[inline(always)]
+    fn normalise(b: [f32; 3], a: [f32; 3]) -> Self {
+        let r = 1.0 / a[0];
+        Self {
+            ff0: b[0] * r,
+            ff1: b[1] * r,
+            ff2: b[2] * r,
+            fb1: a[1] * r,
+            fb2: a[2] * r,
+        }
+    }
+
+    /// The denominator shared by the lowpass, highpass, bandpass and notch.
+    #[inline(always)]
+    fn poles(k: f32, q: f32) -> [f32; 3] {
+        let kk = k * k;
+        [kk + k / q + 1.0, 2.0 * (kk - 1.0), kk - k / q + 1.0]
+    }
+
+    /// -3 dB at `fc` with a `q` of 1/√2, resonating there with more.
+    pub fn lowpass(fc: f32, q: f32) -> Self {
+        let k = prewarp(fc);
+        let kk = k * k;
+        Self::normalise([kk, 2.0 * kk, kk], Self::poles(k, q))
+    }
+
+    pub fn highpass(fc: f32, q: f32) -> Self {
+        let k = prewarp(fc);
+        Self::normalise([1.0, -2.0, 1.0], Self::poles(k, q))
+    }
+
+    /// Unity gain at `fc`, with a bandwidth of `fc / q`.
+    pub fn bandpass(fc: f32, q: f32) -> Self {
+        let k = prewarp(fc);
+        Self::normalise([k / q, 0.0, -k / q], Self::poles(k, q))
+    }
+
+    /// Silent at `fc`, with a stop band `fc / q` wide.
+    pub fn notch(fc: f32, q: f32) -> Self {
+        let k = prewarp(fc);
+        let kk = k * k;
+        Self::normalise([kk + 1.0, 2.0 * (kk - 1.0), kk + 1.0], Self::poles(k, q))
+    }
+
+    /// `gain_db` at `fc`, falling back to unity either side.
+    pub fn peak(fc: f32, q: f32, gain_db: f32) -> Self {
+        let k = prewarp(fc);
+        let kk = k * k;
+        let a = fastpow2f(gain_db * DB_TO_LOG2_SQRT);
+        Self::normalise(
+            [kk + k * a / q + 1.0, 2.0 * (kk - 1.0), kk - k * a / q + 1.0],
+            [
+                kk + k / (a * q) + 1.0,
+                2.0 * (kk - 1.0),
+                kk - k / (a * q) + 1.0,
+            ],
+        )
+    }
+
+    /// `gain_db` below `fc` and unity above. A `q` of 1/√2 is the steepest
+    /// slope without overshoot.
+    pub fn low_shelf(fc: f32, q: f32, gain_db: f32) -> Self {
+        let k = prewarp(fc);
+        let kk = k * k;
+        let a = fastpow2f(gain_db * DB_TO_LOG2_SQRT);
+        let s = fastpow2f(0.5 * gain_db * DB_TO_LOG2_SQRT) * k / q;
+        Self::normalise(
+            [
+                a * (a * kk + s + 1.0),
+                2.0 * a * (a * kk - 1.0),
+                a * (a * kk - s + 1.0),
+            ],
+            [kk + s + a, 2.0 * (kk - a), kk - s + a],
+        )
+    }
+
+    /// Unity below `fc` and `gain_db` above, like `low_shelf`.
+    pub fn high_shelf(fc: f32, q: f32, gain_db: f32) -> Self {
+        let k = prewarp(fc);
+        let kk = k * k;
+        let a = fastpow2f(gain_db * DB_TO_LOG2_SQRT);
+        let s = fastpow2f(0.5 * gain_db * DB_TO_LOG2_SQRT) * k / q;
+        Self::normalise(
+            [a * (kk + s + a), 2.0 * a * (kk - a), a * (kk - s + a)],
+            [a * kk + s + 1.0, 2.0 * (a * kk - 1.0), a * kk - s + 1.0],
+        )
+    }
+}
+
+impl Default for BiquadCoeffs {
+    fn default() -> Self {
+        Self::THRU
+    }
+}
+
+/// A two pole filter, in transposed direct form II.
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+    pub coeffs: BiquadCoeffs,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    pub const fn new(coeffs: BiquadCoeffs) -> Self {
+        Self {
+            coeffs,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Forget past input, as on a note on.
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let c = &self.coeffs;
+        let y = c.ff0 * x + self.z1;
+        self.z1 = c.ff1 * x + self.z2 - c.fb1 * y;
+        self.z2 = c.ff2 * x - c.fb2 * y;
+        y
+    }
+
+    pub fn process_buf(&mut self, buf: &mut [f32]) {
+        for x in buf {
+            *x = self.process(*x);
+        }
+    }
+}
+
+/// A one pole filter, in transposed direct form II. Cheaper than a `Biquad`
+/// where a gentle 6 dB per octave slope is enough.
+#[derive(Clone, Copy)]
+pub struct OnePole {
+    ff0: f32,
+    ff1: f32,
+    fb1: f32,
+    z1: f32,
+}
+
+impl OnePole {
+    /// -3 dB at `fc`.
+    pub fn lowpass(fc: f32) -> Self {
+        let mut filter = Self::THRU;
+        filter.set_lowpass(fc);
+        filter
+    }
+
+    /// -3 dB at `fc`.
+    pub fn highpass(fc: f32) -> Self {
+        let mut filter = Self::THRU;
+        filter.set_highpass(fc);
+        filter
+    }
+
+    const THRU: Self = Self {
+        ff0: 1.0,
+        ff1: 0.0,
+        fb1: 0.0,
+        z1: 0.0,
+    };
+
+    /// Move the cutoff, keeping the filter's state.
+    pub fn set_lowpass(&mut self, fc: f32) {
+        let k = prewarp(fc);
+        let r = 1.0 / (k + 1.0);
+        self.ff0 = k * r;
+        self.ff1 = k * r;
+        self.fb1 = (k - 1.0) * r;
+    }
+
+    /// Move the cutoff, keeping the filter's state.
+    pub fn set_highpass(&mut self, fc: f32) {
+        let k = prewarp(fc);
+        let r = 1.0 / (k + 1.0);
+        self.ff0 = r;
+        self.ff1 = -r;
+        self.fb1 = (k - 1.0) * r;
+    }
+
+    /// Forget past input, as on a note on.
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+    }
+
+    #[inline(always)]
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.ff0 * x + self.z1;
+        self.z1 = self.ff1 * x - self.fb1 * y;
+        y
+    }
+
+    pub fn process_buf(&mut self, buf: &mut [f32]) {
+        for x in buf {
+            *x = self.process(*x);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "internal_luts"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::oscapi::SAMPLERATE;
+
+    /// The gain in dB of `process` for a sine at `f` Hz, once it's settled.
+    fn response(mut process: impl FnMut(f32) -> f32, f: u32) -> f32 {
+        let w = core::f64::consts::TAU * f as f64 / SAMPLERATE as f64;
+        let settle = SAMPLERATE as usize / 10;
+        for n in 0..settle {
+            process((w * n as f64).sin() as f32);
+        }
+
+        // Correlating over a whole second is a whole number of cycles
+        let (mut re, mut im) = (0.0, 0.0);
+        for n in settle..settle + SAMPLERATE as usize {
+            let y = process((w * n as f64).sin() as f32) as f64;
+            re += y * (w * n as f64).sin();
+            im += y * (w * n as f64).cos();
+        }
+        let amplitude = 2.0 * re.hypot(im) / SAMPLERATE as f64;
+        20.0 * amplitude.log10() as f32
+    }
+
+    fn biquad(coeffs: BiquadCoeffs, f: u32) -> f32 {
+        let mut filter = Biquad::new(coeffs);
+        response(|x| filter.process(x), f)
+    }
+
+    fn near(db: f32, expected: f32) -> bool {
+        (db - expected).abs() < 0.1
+    }
+
+    const Q: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn pass_and_stop() {
+        let lp = BiquadCoeffs::lowpass(1000.0, Q);
+        assert!(near(biquad(lp, 50), 0.0));
+        assert!(near(biquad(lp, 1000), -3.0));
+        // 12 dB per octave
+        assert!(biquad(lp, 10000) < -39.0);
+
+        let hp = BiquadCoeffs::highpass(1000.0, Q);
+        assert!(near(biquad(hp, 20000), 0.0));
+        assert!(near(biquad(hp, 1000), -3.0));
+        assert!(biquad(hp, 100) < -39.0);
+
+        let bp = BiquadCoeffs::bandpass(1000.0, 2.0);
+        assert!(near(biquad(bp, 1000), 0.0));
+        assert!(biquad(bp, 100) < -20.0);
+        assert!(biquad(bp, 10000) < -20.0);
+
+        let notch = BiquadCoeffs::notch(1000.0, 2.0);
+        assert!(biquad(notch, 1000) < -40.0);
+        assert!(near(biquad(notch, 50), 0.0));
+        assert!(near(biquad(notch, 20000), 0.0));
+    }
+
+    #[test]
+    fn peak_and_shelves() {
+        let peak = BiquadCoeffs::peak(1000.0, 2.0, 6.0);
+        assert!(near(biquad(peak, 1000), 6.0));
+        assert!(near(biquad(peak, 20), 0.0));
+        let dip = BiquadCoeffs::peak(1000.0, 2.0, -6.0);
+        assert!(near(biquad(dip, 1000), -6.0));
+
+        let low = BiquadCoeffs::low_shelf(200.0, Q, 6.0);
+        assert!(near(biquad(low, 10), 6.0));
+        assert!(near(biquad(low, 200), 3.0));
+        assert!(near(biquad(low, 10000), 0.0));
+
+        let high = BiquadCoeffs::high_shelf(5000.0, Q, -6.0);
+        assert!(near(biquad(high, 20), 0.0));
+        assert!(near(biquad(high, 5000), -3.0));
+        assert!(near(biquad(high, 23000), -6.0));
+    }
+
+    #[test]
+    fn one_pole() {
+        let mut lp = OnePole::lowpass(1000.0);
+        assert!(near(response(|x| lp.process(x), 1000), -3.0));
+        // 6 dB per octave
+        let mut lp = OnePole::lowpass(1000.0);
+        assert!(response(|x| lp.process(x), 10000) < -19.0);
+
+        let mut hp = OnePole::highpass(1000.0);
+        assert!(near(response(|x| hp.process(x), 1000), -3.0));
+        let mut hp = OnePole::highpass(1000.0);
+        assert!(near(response(|x| hp.process(x), 20000), 0.0));
+    }
+}
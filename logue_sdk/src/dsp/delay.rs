@@ -0,0 +1,185 @@
+//! Delay lines, after the SDK's `delayline.hpp`.
+
+use super::{cubicintf, linintf};
+
+/// What a delay line holds: a sample, or a frame of one per channel.
+pub trait Frame: Copy {
+    const ZERO: Self;
+
+    /// Linear interpolation from `x0` to `x1`.
+    fn lerp(fr: f32, x0: Self, x1: Self) -> Self;
+
+    /// Cubic interpolation from `x0` to `x1`, with the points either side.
+    fn cubic(fr: f32, xm1: Self, x0: Self, x1: Self, x2: Self) -> Self;
+}
+
+impl Frame for f32 {
+    const ZERO: Self = 0.0;
+
+    #[inline(always)]
+    fn lerp(fr: f32, x0: Self, x1: Self) -> Self {
+        linintf(fr, x0, x1)
+    }
+
+    #[inline(always)]
+    fn cubic(fr: f32, xm1: Self, x0: Self, x1: Self, x2: Self) -> Self {
+        cubicintf(fr, xm1, x0, x1, x2)
+    }
+}
+
+impl Frame for [f32; 2] {
+    const ZERO: Self = [0.0; 2];
+
+    #[inline(always)]
+    fn lerp(fr: f32, x0: Self, x1: Self) -> Self {
+        [linintf(fr, x0[0], x1[0]), linintf(fr, x0[1], x1[1])]
+    }
+
+    #[inline(always)]
+    fn cubic(fr: f32, xm1: Self, x0: Self, x1: Self, x2: Self) -> Self {
+        [
+            cubicintf(fr, xm1[0], x0[0], x1[0], x2[0]),
+            cubicintf(fr, xm1[1], x0[1], x1[1], x2[1]),
+        ]
+    }
+}
+
+/// A ring buffer of the last `capacity()` frames written, over a buffer the
+/// unit owns for its lifetime, such as one from an `SdramAllocator`.
+///
+/// Delays count back from the last frame written, which is at a delay of 1,
+/// so reading a delay of `d` before writing each frame delays the input by
+/// `d` frames. Delays wrap around at the capacity.
+pub struct DelayLine<T: Frame + 'static = f32> {
+    buf: &'static mut [T],
+    mask: usize,
+    write: usize,
+}
+
+/// A delay line of interleaved left and right samples.
+pub type StereoDelayLine = DelayLine<[f32; 2]>;
+
+impl<T: Frame> DelayLine<T> {
+    /// A silent delay line over `buf`, whose length must be a power of two.
+    pub fn new(buf: &'static mut [T]) -> Self {
+        assert!(
+            buf.len().is_power_of_two(),
+            "a delay line's length must be a power of two"
+        );
+        buf.fill(T::ZERO);
+        Self {
+            mask: buf.len() - 1,
+            buf,
+            write: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Silence the line, as on a resume.
+    pub fn clear(&mut self) {
+        self.buf.fill(T::ZERO);
+    }
+
+    #[inline(always)]
+    pub fn write(&mut self, x: T) {
+        self.buf[self.write & self.mask] = x;
+        self.write = self.write.wrapping_sub(1);
+    }
+
+    /// The frame written `delay` frames ago, so 1 is the latest. 0 is the
+    /// oldest, about to be overwritten.
+    #[inline(always)]
+    pub fn read(&self, delay: usize) -> T {
+        self.buf[self.write.wrapping_add(delay) & self.mask]
+    }
+
+    /// Linear interpolation between the frames either side of `delay`, which
+    /// is at least 1.
+    #[inline(always)]
+    pub fn read_linear(&self, delay: f32) -> T {
+        let delay = delay.max(1.0);
+        let base = delay as usize;
+        let fr = delay - base as f32;
+        T::lerp(fr, self.read(base), self.read(base + 1))
+    }
+
+    /// Cubic interpolation over the two frames either side of `delay`, which
+    /// is at least 2 so that the newer two have been written. Smoother than
+    /// `read_linear` when the delay is modulated, for twice the reads.
+    #[inline(always)]
+    pub fn read_cubic(&self, delay: f32) -> T {
+        let delay = delay.max(2.0);
+        let base = delay as usize;
+        let fr = delay - base as f32;
+        T::cubic(
+            fr,
+            self.read(base.wrapping_sub(1)),
+            self.read(base),
+            self.read(base + 1),
+            self.read(base + 2),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::vec;
+
+    fn line<T: Frame>(len: usize) -> DelayLine<T> {
+        DelayLine::new(vec![T::ZERO; len].leak())
+    }
+
+    #[test]
+    fn delays_by_whole_frames() {
+        let mut delay = line::<f32>(8);
+        let mut out = [0.0; 10];
+        for (n, y) in out.iter_mut().enumerate() {
+            *y = delay.read(3);
+            delay.write(n as f32 + 1.0);
+        }
+        assert_eq!(out, [0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+
+        // The oldest frame is a whole capacity back
+        assert_eq!(delay.read(8), 3.0);
+    }
+
+    #[test]
+    fn interpolates_between_frames() {
+        let mut delay = line::<f32>(16);
+        for n in 0..16 {
+            delay.write(n as f32);
+        }
+        // Both are exact on a ramp
+        assert_eq!(delay.read_linear(2.25), 13.75);
+        assert_eq!(delay.read_cubic(2.25), 13.75);
+        assert_eq!(delay.read_cubic(5.0), delay.read(5));
+    }
+
+    #[test]
+    fn short_delays_read_the_latest_frames() {
+        let mut delay = line::<f32>(16);
+        for n in 0..16 {
+            delay.write(n as f32);
+        }
+        // Not the oldest frame, 0, which is next to be overwritten
+        assert_eq!(delay.read_linear(0.0), 15.0);
+        assert_eq!(delay.read_linear(0.5), 15.0);
+        assert_eq!(delay.read_cubic(0.0), 14.0);
+        assert_eq!(delay.read_cubic(1.5), 14.0);
+    }
+
+    #[test]
+    fn stereo_channels_are_separate() {
+        let mut delay = line::<[f32; 2]>(4);
+        delay.write([1.0, -1.0]);
+        delay.write([2.0, -2.0]);
+        assert_eq!(delay.read(2), [1.0, -1.0]);
+        assert_eq!(delay.read_linear(1.5), [1.5, -1.5]);
+    }
+}
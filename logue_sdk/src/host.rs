@@ -0,0 +1,53 @@
+//! Implementations of the firmware functions that `oscapi` expects to be
+//! provided externally, so units can be built and run on a development
+//! machine.
+
+extern crate std;
+
+use core::cell::Cell;
+use std::vec;
+
+use crate::dsp::q31_to_f32;
+use crate::sdram::SDRAM_SIZE;
+
+const DEFAULT_WHITE_SEED: u32 = 0x2545_f491;
+
+std::thread_local! {
+    // Per thread, so renders on parallel test threads each get the whole
+    // sequence
+    static WHITE_STATE: Cell<u32> = const { Cell::new(DEFAULT_WHITE_SEED) };
+}
+
+#[inline(always)]
+fn xorshift32(mut x: u32) -> u32 {
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    x
+}
+
+/// Restart the sequence returned by `osc_white` on this thread, so renders
+/// are reproducible.
+pub fn seed_white(seed: u32) {
+    // Zero is a fixed point of xorshift
+    let seed = if seed == 0 { DEFAULT_WHITE_SEED } else { seed };
+    WHITE_STATE.with(|state| state.set(seed));
+}
+
+#[no_mangle]
+pub extern "C" fn _osc_white() -> f32 {
+    let x = WHITE_STATE.with(|state| {
+        let x = xorshift32(state.get());
+        state.set(x);
+        x
+    });
+    q31_to_f32(x as i32)
+}
+
+/// A fresh SDRAM region for each unit instance. These are leaked, as
+/// allocations from them last as long as the program.
+pub(crate) fn sdram_region() -> (usize, usize) {
+    let region: &'static mut [u64] = vec![0; SDRAM_SIZE / 8].leak();
+    let start = region.as_mut_ptr() as usize;
+    (start, start + SDRAM_SIZE)
+}
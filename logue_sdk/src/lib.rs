@@ -1,6 +1,21 @@
 #![no_std]
 
+pub mod delfx;
 pub mod dsp;
+#[cfg(feature = "host")]
+pub mod host;
 #[cfg(feature = "internal_luts")]
 pub mod lut;
+pub mod manifest;
+pub mod math;
+pub mod modfx;
 pub mod oscapi;
+#[cfg(any(feature = "host", target_arch = "wasm32"))]
+pub mod poly;
+pub mod revfx;
+#[cfg(target_os = "none")]
+mod runtime;
+pub mod sdram;
+mod sdram_fx;
+#[cfg(any(feature = "nts1_mkii", feature = "minilogue_xd", feature = "drumlogue"))]
+pub mod unit;
@@ -10,3 +10,39 @@ mod waves_d;
 mod waves_e;
 mod waves_f;
 mod wavetable_lut;
+
+use crate::oscapi::{wt_par_notes, wt_saw_notes, wt_sqr_notes};
+
+/// The fractional index of the table for `note`, given the note each table
+/// starts at. Between two of those notes it's the distance from one to the
+/// next, so `osc_bl2_*` can crossfade.
+fn bl_idx(notes: &[u8; 7], note: f32) -> f32 {
+    if note <= notes[0] as f32 {
+        return 0.0;
+    }
+    for (i, pair) in notes.windows(2).enumerate() {
+        let (n0, n1) = (pair[0] as f32, pair[1] as f32);
+        if note < n1 {
+            return i as f32 + (note - n0) / (n1 - n0);
+        }
+    }
+    (notes.len() - 1) as f32
+}
+
+// The firmware functions that only look up the tables above, for builds that
+// link their own copies of them.
+
+#[no_mangle]
+pub extern "C" fn _osc_bl_saw_idx(note: f32) -> f32 {
+    bl_idx(unsafe { &wt_saw_notes }, note)
+}
+
+#[no_mangle]
+pub extern "C" fn _osc_bl_sqr_idx(note: f32) -> f32 {
+    bl_idx(unsafe { &wt_sqr_notes }, note)
+}
+
+#[no_mangle]
+pub extern "C" fn _osc_bl_par_idx(note: f32) -> f32 {
+    bl_idx(unsafe { &wt_par_notes }, note)
+}
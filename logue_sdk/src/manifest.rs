@@ -0,0 +1,129 @@
+//! The unit description that goes in `manifest.json` alongside the payload.
+//!
+//! A unit declares its manifest as a `const` in a file that is both compiled
+//! into the unit and `include!`d by its build script, so `logue_sdk_build` can
+//! write `manifest.json` from the same declaration the code uses.
+
+use crate::oscapi::Platform;
+
+/// Most parameters a v1 unit can declare, `Param1` to `Param6`.
+pub const MAX_PARAMS: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Module {
+    Osc,
+    ModFx,
+    DelFx,
+    RevFx,
+}
+
+impl Module {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Module::Osc => "osc",
+            Module::ModFx => "modfx",
+            Module::DelFx => "delfx",
+            Module::RevFx => "revfx",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Module::Osc, Module::ModFx, Module::DelFx, Module::RevFx]
+            .into_iter()
+            .find(|m| m.name() == name)
+    }
+}
+
+impl Platform {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Platform::Prologue => "prologue",
+            Platform::MinilogueXD => "minilogue-xd",
+            Platform::NutektDigital => "nutekt-digital",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Platform::Prologue,
+            Platform::MinilogueXD,
+            Platform::NutektDigital,
+        ]
+        .into_iter()
+        .find(|p| p.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParamUnit {
+    None,
+    Percent,
+}
+
+impl ParamUnit {
+    pub const fn name(self) -> &'static str {
+        match self {
+            ParamUnit::None => "",
+            ParamUnit::Percent => "%",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ParamUnit::None, ParamUnit::Percent]
+            .into_iter()
+            .find(|u| u.name() == name)
+    }
+}
+
+/// One of `Param1` to `Param6`, in order. The firmware sends values between
+/// `min` and `max` inclusive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParamDesc {
+    pub name: &'static str,
+    pub min: i16,
+    pub max: i16,
+    pub unit: ParamUnit,
+}
+
+impl ParamDesc {
+    pub const fn new(name: &'static str, min: i16, max: i16, unit: ParamUnit) -> Self {
+        assert!(min <= max, "parameter minimum is above its maximum");
+        Self {
+            name,
+            min,
+            max,
+            unit,
+        }
+    }
+}
+
+pub struct Manifest {
+    pub platform: Platform,
+    pub module: Module,
+    pub name: &'static str,
+    pub dev_id: u32,
+    pub prg_id: u32,
+    /// Packed as major, minor and patch bytes, like `API_VERSION`.
+    pub version: u32,
+    pub params: &'static [ParamDesc],
+}
+
+impl Manifest {
+    pub const fn new(
+        platform: Platform,
+        module: Module,
+        name: &'static str,
+        params: &'static [ParamDesc],
+    ) -> Self {
+        assert!(params.len() <= MAX_PARAMS, "too many parameters");
+        Self {
+            platform,
+            module,
+            name,
+            dev_id: 0,
+            prg_id: 0,
+            version: 0x01_00_00,
+            params,
+        }
+    }
+}
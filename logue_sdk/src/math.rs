@@ -0,0 +1,169 @@
+//! Math functions for oscillators: lookups in the firmware's math tables, and
+//! the fast approximations the SDK's `float_math.h` has for the rest.
+//!
+//! The error bounds given are over the whole of each function's documented
+//! range, checked against `std` in the tests below.
+
+use crate::dsp::linintf;
+use crate::oscapi::{log_lut_f, sqrtm2log_lut_f, tanpi_lut_f};
+
+/// Intervals in each math table, over the range of the function.
+const LUT_SIZE: usize = 256;
+
+/// Smallest argument of `osc_logf` and `osc_sqrtm2logf`, which the first
+/// entry of their tables is for.
+const LOG_MIN: f32 = 1e-5;
+
+const TANPI_MIN: f32 = 1e-4;
+const TANPI_MAX: f32 = 0.49;
+
+/// Interpolate in a math table at `idxf` in \[0, `LUT_SIZE`\].
+#[inline(always)]
+fn lut_lookup(lut: &[f32; LUT_SIZE + 1], idxf: f32) -> f32 {
+    let idx = (idxf as usize).min(LUT_SIZE - 1);
+    linintf(idxf - idx as f32, lut[idx], lut[idx + 1])
+}
+
+/// ln(x) for `x` in \[1e-5, 1\], clipped to that range.
+///
+/// Within 0.06 of ln(x) from 1/256 up, and within 5e-4 from 1/16 up. The
+/// table only has multiples of 1/256, so below that it's a rough guess.
+#[inline(always)]
+pub fn osc_logf(x: f32) -> f32 {
+    let x = x.clamp(LOG_MIN, 1.0);
+    lut_lookup(unsafe { &log_lut_f }, x * LUT_SIZE as f32)
+}
+
+/// sqrt(-2 ln(x)) for `x` in \[1e-5, 1\], clipped to that range, as used to
+/// turn uniform noise into Gaussian.
+///
+/// Within 0.02 of the exact value from 1/256 up to 1 - 1/256, and within
+/// 0.03 above that. Like `osc_logf` it's a rough guess below 1/256.
+#[inline(always)]
+pub fn osc_sqrtm2logf(x: f32) -> f32 {
+    let x = x.clamp(LOG_MIN, 1.0);
+    lut_lookup(unsafe { &sqrtm2log_lut_f }, x * LUT_SIZE as f32)
+}
+
+/// tan(πx) for `x` in \[1e-4, 0.49\], clipped to that range, as used to
+/// prewarp filter cutoffs given as a fraction of the sample rate.
+///
+/// Within 0.05% of tan(πx) up to 0.45, and within 1% above that.
+#[inline(always)]
+pub fn osc_tanpif(x: f32) -> f32 {
+    let x = x.clamp(TANPI_MIN, TANPI_MAX);
+    lut_lookup(unsafe { &tanpi_lut_f }, x * (LUT_SIZE as f32 / TANPI_MAX))
+}
+
+/// 2^p, within 0.01% for `p` in \[-126, 127\]. Below that it's clipped.
+#[inline(always)]
+pub fn fastpow2f(p: f32) -> f32 {
+    let offset = if p < 0.0 { 1.0 } else { 0.0 };
+    let clipp = p.max(-126.0);
+    let z = clipp - (clipp as i32) as f32 + offset;
+    let bits =
+        (1 << 23) as f32 * (clipp + 121.274_06 + 27.728_024 / (4.842_525_6 - z) - 1.490_129_1 * z);
+    f32::from_bits(bits as u32)
+}
+
+/// 2^p, within 6% for `p` in \[-126, 127\]. Below that it's clipped.
+#[inline(always)]
+pub fn fasterpow2f(p: f32) -> f32 {
+    let clipp = p.max(-126.0);
+    f32::from_bits(((1 << 23) as f32 * (clipp + 126.942_695)) as u32)
+}
+
+/// e^p, with the error of `fasterpow2f`.
+#[inline(always)]
+pub fn fasterexpf(p: f32) -> f32 {
+    fasterpow2f(core::f32::consts::LOG2_E * p)
+}
+
+/// tanh(p), within 0.02 everywhere.
+#[inline(always)]
+pub fn fastertanhf(p: f32) -> f32 {
+    -1.0 + 2.0 / (1.0 + fasterexpf(-2.0 * p))
+}
+
+/// sin(x), within 1e-3 for `x` in \[-π, π\].
+#[inline(always)]
+pub fn fastersinf(x: f32) -> f32 {
+    const FOUR_OVER_PI: f32 = 1.273_239_5;
+    const FOUR_OVER_PI_SQ: f32 = 0.405_284_74;
+    const Q: f32 = 0.776_330_23;
+    const P: f32 = 0.223_085_1;
+
+    let qpprox = FOUR_OVER_PI * x - FOUR_OVER_PI_SQ * x * x.abs();
+    qpprox * (Q + P.copysign(x) * qpprox)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    /// The largest error of `f` from `reference` at `n` even steps over
+    /// \[`lo`, `hi`\], relative to `reference` when `relative` is set.
+    fn max_error(
+        f: impl Fn(f32) -> f32,
+        reference: impl Fn(f64) -> f64,
+        lo: f32,
+        hi: f32,
+        relative: bool,
+    ) -> f64 {
+        let n = 100_000;
+        (0..=n)
+            .map(|i| lo + (hi - lo) * i as f32 / n as f32)
+            .map(|x| {
+                let expected = reference(x as f64);
+                let error = (f(x) as f64 - expected).abs();
+                if relative {
+                    error / expected.abs()
+                } else {
+                    error
+                }
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    #[cfg(feature = "internal_luts")]
+    fn log_tables() {
+        let ln = |x: f64| x.ln();
+        assert!(max_error(osc_logf, ln, 1.0 / 256.0, 1.0, false) < 0.06);
+        assert!(max_error(osc_logf, ln, 1.0 / 16.0, 1.0, false) < 5e-4);
+
+        let sqrtm2log = |x: f64| (-2.0 * x.ln()).sqrt();
+        let top = 1.0 - 1.0 / 256.0;
+        assert!(max_error(osc_sqrtm2logf, sqrtm2log, 1.0 / 256.0, top, false) < 0.02);
+        assert!(max_error(osc_sqrtm2logf, sqrtm2log, top, 1.0, false) < 0.03);
+    }
+
+    #[test]
+    #[cfg(feature = "internal_luts")]
+    fn tanpi_table() {
+        let tanpi = |x: f64| (core::f64::consts::PI * x).tan();
+        assert!(max_error(osc_tanpif, tanpi, TANPI_MIN, 0.45, true) < 5e-4);
+        assert!(max_error(osc_tanpif, tanpi, 0.45, TANPI_MAX, true) < 0.01);
+        assert_eq!(osc_tanpif(1.0), osc_tanpif(TANPI_MAX));
+    }
+
+    #[test]
+    fn pow2() {
+        let pow2 = |x: f64| x.exp2();
+        assert!(max_error(fastpow2f, pow2, -126.0, 127.0, true) < 1e-4);
+        assert!(max_error(fasterpow2f, pow2, -126.0, 127.0, true) < 0.06);
+        let exp = |x: f64| x.exp();
+        assert!(max_error(fasterexpf, exp, -80.0, 80.0, true) < 0.06);
+    }
+
+    #[test]
+    fn tanh_and_sin() {
+        let tanh = |x: f64| x.tanh();
+        assert!(max_error(fastertanhf, tanh, -20.0, 20.0, false) < 0.02);
+        let pi = core::f32::consts::PI;
+        let sin = |x: f64| x.sin();
+        assert!(max_error(fastersinf, sin, -pi, pi, false) < 1e-3);
+    }
+}
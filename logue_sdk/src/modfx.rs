@@ -0,0 +1,147 @@
+use core::mem::{transmute, MaybeUninit};
+use core::slice;
+
+use crate::oscapi::Platform;
+
+mod logue_interface;
+
+pub const HOOKS_MAGIC: [u8; 4] = *b"UMFX";
+
+#[repr(u8)]
+pub enum ModFxParam {
+    ParamTime = 0,
+    ParamDepth,
+}
+
+impl TryFrom<u8> for ModFxParam {
+    type Error = ();
+
+    fn try_from(x: u8) -> Result<ModFxParam, Self::Error> {
+        if x > ModFxParam::ParamDepth as u8 {
+            Err(())
+        } else {
+            Ok(unsafe { transmute::<u8, ModFxParam>(x) })
+        }
+    }
+}
+
+type UserModFxFuncEntry = extern "C" fn(platform: u32, api: u32) -> ();
+type UserModFxFuncProcess = extern "C" fn(
+    main_xn: *const f32,
+    main_yn: *mut f32,
+    sub_xn: *const f32,
+    sub_yn: *mut f32,
+    frames: u32,
+);
+type UserModFxFuncSuspend = extern "C" fn();
+type UserModFxFuncResume = extern "C" fn();
+type UserModFxFuncParam = extern "C" fn(index: u8, value: i32);
+
+#[repr(C, packed(1))]
+pub struct UserModFxHookTable {
+    pub magic: [u8; 4],
+    pub api: u32,
+    pub platform: u8,
+    pub reserved0: [u8; 7],
+    pub func_entry: UserModFxFuncEntry,
+    pub func_process: UserModFxFuncProcess,
+    pub func_suspend: UserModFxFuncSuspend,
+    pub func_resume: UserModFxFuncResume,
+    pub func_param: UserModFxFuncParam,
+}
+
+impl UserModFxHookTable {
+    /// The `func_*` fields in table order, without the prefix.
+    pub const HOOK_NAMES: [&'static str; 5] = ["entry", "process", "suspend", "resume", "param"];
+}
+
+/// A modulation effect. Buffers are interleaved stereo, so hold twice as many
+/// samples as frames. The sub buffers carry the prologue's sub timbre, and
+/// are empty on platforms without one.
+pub trait UserModFx {
+    const PLATFORM: Platform;
+
+    fn init(_platform: u32, _api: u32) -> Self;
+    fn process(
+        &mut self,
+        main_xn: &[f32],
+        main_yn: &mut [f32],
+        sub_xn: &[f32],
+        sub_yn: &mut [f32],
+    ) {
+        // Either side of the sub bus may be missing without the other
+        for (y, x) in main_yn.iter_mut().zip(main_xn) {
+            *y = *x;
+        }
+        for (y, x) in sub_yn.iter_mut().zip(sub_xn) {
+            *y = *x;
+        }
+    }
+    fn suspend(&mut self) {}
+    fn resume(&mut self) {}
+    /// `value` is a q31 fraction of the knob range.
+    fn param(&mut self, _param: ModFxParam, _value: i32) {}
+}
+
+pub fn init_cb<T: UserModFx>(instance: &mut MaybeUninit<T>, platform: u32, api: u32) {
+    #[cfg(target_os = "none")]
+    unsafe {
+        crate::runtime::init();
+    }
+
+    instance.write(T::init(platform, api));
+}
+
+unsafe fn stereo<'a>(p: *const f32, frames: u32) -> &'a [f32] {
+    if p.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(p, frames as usize * 2)
+    }
+}
+
+unsafe fn stereo_mut<'a>(p: *mut f32, frames: u32) -> &'a mut [f32] {
+    if p.is_null() {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(p, frames as usize * 2)
+    }
+}
+
+/// # Safety
+/// `instance` must have been initialised by `init_cb`. Each buffer must be
+/// null or valid for `frames` stereo frames, and the outputs must not overlap
+/// each other or the inputs.
+pub unsafe fn process_cb<T: UserModFx>(
+    instance: &mut MaybeUninit<T>,
+    main_xn: *const f32,
+    main_yn: *mut f32,
+    sub_xn: *const f32,
+    sub_yn: *mut f32,
+    frames: u32,
+) {
+    let instance = instance.assume_init_mut();
+    instance.process(
+        stereo(main_xn, frames),
+        stereo_mut(main_yn, frames),
+        stereo(sub_xn, frames),
+        stereo_mut(sub_yn, frames),
+    );
+}
+
+pub fn suspend_cb<T: UserModFx>(instance: &mut MaybeUninit<T>) {
+    let instance = unsafe { instance.assume_init_mut() };
+    instance.suspend();
+}
+
+pub fn resume_cb<T: UserModFx>(instance: &mut MaybeUninit<T>) {
+    let instance = unsafe { instance.assume_init_mut() };
+    instance.resume();
+}
+
+pub fn param_cb<T: UserModFx>(instance: &mut MaybeUninit<T>, index: u8, value: i32) {
+    if let Ok(param) = index.try_into() {
+        let instance = unsafe { instance.assume_init_mut() };
+        instance.param(param, value);
+    }
+}
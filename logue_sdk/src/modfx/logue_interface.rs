@@ -0,0 +1,63 @@
+#[macro_export]
+macro_rules! user_modfx_hooks {
+    ($fx:ty) => {
+        static mut INSTANCE: core::mem::MaybeUninit<$fx> = core::mem::MaybeUninit::uninit();
+
+        extern "C" fn func_entry(platform: u32, api: u32) {
+            unsafe {
+                $crate::modfx::init_cb(&mut *core::ptr::addr_of_mut!(INSTANCE), platform, api);
+            }
+        }
+
+        extern "C" fn func_process(
+            main_xn: *const f32,
+            main_yn: *mut f32,
+            sub_xn: *const f32,
+            sub_yn: *mut f32,
+            frames: u32,
+        ) {
+            unsafe {
+                $crate::modfx::process_cb(
+                    &mut *core::ptr::addr_of_mut!(INSTANCE),
+                    main_xn,
+                    main_yn,
+                    sub_xn,
+                    sub_yn,
+                    frames,
+                );
+            }
+        }
+
+        extern "C" fn func_suspend() {
+            unsafe {
+                $crate::modfx::suspend_cb(&mut *core::ptr::addr_of_mut!(INSTANCE));
+            }
+        }
+
+        extern "C" fn func_resume() {
+            unsafe {
+                $crate::modfx::resume_cb(&mut *core::ptr::addr_of_mut!(INSTANCE));
+            }
+        }
+
+        extern "C" fn func_param(index: u8, value: i32) {
+            unsafe {
+                $crate::modfx::param_cb(&mut *core::ptr::addr_of_mut!(INSTANCE), index, value);
+            }
+        }
+
+        #[link_section = ".hooks"]
+        #[no_mangle]
+        static hook_table: $crate::modfx::UserModFxHookTable = $crate::modfx::UserModFxHookTable {
+            magic: $crate::modfx::HOOKS_MAGIC,
+            api: $crate::oscapi::API_VERSION,
+            platform: <$fx as $crate::modfx::UserModFx>::PLATFORM as u8,
+            reserved0: [0, 0, 0, 0, 0, 0, 0],
+            func_entry,
+            func_process,
+            func_suspend,
+            func_resume,
+            func_param,
+        };
+    };
+}
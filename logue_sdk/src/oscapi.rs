@@ -6,10 +6,17 @@ mod logue_interface;
 mod wasm_interface;
 
 use crate::dsp::linintf;
+use crate::manifest::ParamDesc;
 
 pub const SAMPLERATE: u32 = 48_000;
 pub const SAMPLERATE_RECIPF: f32 = 2.083_333_3e-5_f32;
 
+/// Largest buffer the firmware asks an oscillator to fill in one call.
+pub const MAX_FRAMES: usize = 64;
+
+pub const API_VERSION: u32 = 0x01_01_00;
+pub const HOOKS_MAGIC: [u8; 4] = *b"UOSC";
+
 pub const NOTE_MOD_FSCALE: f32 = 0.00392156862745098f32;
 pub const NOTE_MAX_HZ: f32 = 23679.643054f32;
 
@@ -48,6 +55,9 @@ extern "C" {
 mod internal {
     extern "C" {
         pub fn _osc_white() -> f32;
+        pub fn _osc_bl_saw_idx(note: f32) -> f32;
+        pub fn _osc_bl_sqr_idx(note: f32) -> f32;
+        pub fn _osc_bl_par_idx(note: f32) -> f32;
     }
 }
 
@@ -97,6 +107,152 @@ pub fn osc_wave_scanf(wave: &[f32; 129], x: f32) -> f32 {
     )
 }
 
+/// Points in each of the standard wave tables, which hold half a period.
+const WT_SIZE: usize = 128;
+/// Band-limited versions of each standard wave, one per entry in its notes
+/// table, with fewer harmonics as the index goes up.
+const WT_TABLES: usize = 7;
+
+/// Where phase `x` falls in a half period table: the two points to
+/// interpolate between, how far between them, and the sign to apply. The
+/// second half of the period is the first mirrored and negated.
+#[inline(always)]
+fn wt_half_period(x: f32) -> (usize, usize, f32, f32) {
+    let p: f32 = x - ((x as u32) as f32);
+    let x0f: f32 = 2.0 * p * WT_SIZE as f32;
+    let x0p: usize = x0f as usize;
+    let fr = x0f - x0p as f32;
+
+    if x0p >= WT_SIZE {
+        let x0 = WT_SIZE - (x0p & (WT_SIZE - 1));
+        (x0, x0 - 1, fr, -1.0)
+    } else {
+        (x0p, x0p + 1, fr, 1.0)
+    }
+}
+
+#[inline(always)]
+fn wt_table(lut: &[f32; 903], idx: usize) -> &[f32] {
+    let start = idx.min(WT_TABLES - 1) * (WT_SIZE + 1);
+    &lut[start..start + WT_SIZE + 1]
+}
+
+#[inline(always)]
+fn osc_bl_wavef(lut: &[f32; 903], x: f32, idx: u8) -> f32 {
+    let (x0, x1, fr, sign) = wt_half_period(x);
+    let wt = wt_table(lut, idx as usize);
+    sign * linintf(fr, wt[x0], wt[x1])
+}
+
+#[inline(always)]
+fn osc_bl2_wavef(lut: &[f32; 903], x: f32, idx: f32) -> f32 {
+    let (x0, x1, fr, sign) = wt_half_period(x);
+    let idx0 = idx as usize;
+    let wt0 = wt_table(lut, idx0);
+    let wt1 = wt_table(lut, idx0 + 1);
+    let y0 = linintf(fr, wt0[x0], wt0[x1]);
+    let y1 = linintf(fr, wt1[x0], wt1[x1]);
+    sign * linintf(idx - idx0 as f32, y0, y1)
+}
+
+/// Sine at phase `x` in \[0, 1).
+#[inline(always)]
+pub fn osc_sinf(x: f32) -> f32 {
+    let p: f32 = x - ((x as u32) as f32);
+    let x0f: f32 = 2.0 * p * WT_SIZE as f32;
+    let x0p: usize = x0f as usize;
+    let x0 = x0p & (WT_SIZE - 1);
+    let y0 = unsafe { linintf(x0f - x0p as f32, wt_sine_lut_f[x0], wt_sine_lut_f[x0 + 1]) };
+    if x0p < WT_SIZE {
+        y0
+    } else {
+        -y0
+    }
+}
+
+/// Cosine at phase `x` in \[0, 1).
+#[inline(always)]
+pub fn osc_cosf(x: f32) -> f32 {
+    osc_sinf(x + 0.25)
+}
+
+/// Saw at phase `x` in \[0, 1), with all the harmonics the tables hold. Only
+/// alias free for low notes; see `osc_bl2_sawf`.
+#[inline(always)]
+pub fn osc_sawf(x: f32) -> f32 {
+    osc_bl_sawf(x, 0)
+}
+
+/// Square at phase `x` in \[0, 1), like `osc_sawf`.
+#[inline(always)]
+pub fn osc_sqrf(x: f32) -> f32 {
+    osc_bl_sqrf(x, 0)
+}
+
+/// Parabolic wave at phase `x` in \[0, 1), like `osc_sawf`.
+#[inline(always)]
+pub fn osc_parf(x: f32) -> f32 {
+    osc_bl_parf(x, 0)
+}
+
+/// Saw at phase `x` from band-limited table `idx`, as from `osc_bl_saw_idx`
+/// rounded down.
+#[inline(always)]
+pub fn osc_bl_sawf(x: f32, idx: u8) -> f32 {
+    osc_bl_wavef(unsafe { &wt_saw_lut_f }, x, idx)
+}
+
+/// Square at phase `x` from band-limited table `idx`.
+#[inline(always)]
+pub fn osc_bl_sqrf(x: f32, idx: u8) -> f32 {
+    osc_bl_wavef(unsafe { &wt_sqr_lut_f }, x, idx)
+}
+
+/// Parabolic wave at phase `x` from band-limited table `idx`.
+#[inline(always)]
+pub fn osc_bl_parf(x: f32, idx: u8) -> f32 {
+    osc_bl_wavef(unsafe { &wt_par_lut_f }, x, idx)
+}
+
+/// Saw at phase `x`, interpolating between the band-limited tables on
+/// either side of the fractional `idx` from `osc_bl_saw_idx`, so the tone
+/// doesn't step as the pitch moves.
+#[inline(always)]
+pub fn osc_bl2_sawf(x: f32, idx: f32) -> f32 {
+    osc_bl2_wavef(unsafe { &wt_saw_lut_f }, x, idx)
+}
+
+/// Square at phase `x`, like `osc_bl2_sawf`.
+#[inline(always)]
+pub fn osc_bl2_sqrf(x: f32, idx: f32) -> f32 {
+    osc_bl2_wavef(unsafe { &wt_sqr_lut_f }, x, idx)
+}
+
+/// Parabolic wave at phase `x`, like `osc_bl2_sawf`.
+#[inline(always)]
+pub fn osc_bl2_parf(x: f32, idx: f32) -> f32 {
+    osc_bl2_wavef(unsafe { &wt_par_lut_f }, x, idx)
+}
+
+/// The band-limited saw table to play fractional MIDI `note` from, for
+/// `osc_bl_sawf` and `osc_bl2_sawf`.
+#[inline(always)]
+pub fn osc_bl_saw_idx(note: f32) -> f32 {
+    unsafe { internal::_osc_bl_saw_idx(note) }
+}
+
+/// The band-limited square table to play `note` from.
+#[inline(always)]
+pub fn osc_bl_sqr_idx(note: f32) -> f32 {
+    unsafe { internal::_osc_bl_sqr_idx(note) }
+}
+
+/// The band-limited parabolic table to play `note` from.
+#[inline(always)]
+pub fn osc_bl_par_idx(note: f32) -> f32 {
+    unsafe { internal::_osc_bl_par_idx(note) }
+}
+
 #[inline(always)]
 pub fn osc_notehz(note: u8) -> f32 {
     unsafe {
@@ -126,13 +282,28 @@ pub struct UserOscParam {
 }
 
 #[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Platform {
     Prologue = 1,
     MinilogueXD = 2,
     NutektDigital = 3,
 }
 
+impl TryFrom<u8> for Platform {
+    type Error = ();
+
+    fn try_from(x: u8) -> Result<Platform, Self::Error> {
+        match x {
+            1 => Ok(Platform::Prologue),
+            2 => Ok(Platform::MinilogueXD),
+            3 => Ok(Platform::NutektDigital),
+            _ => Err(()),
+        }
+    }
+}
+
 #[repr(u16)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum OscParam {
     Param1 = 0,
     Param2,
@@ -179,40 +350,27 @@ pub struct UserOscHookTable {
     pub func_param: UserOscFuncParam,
 }
 
-type InitFn = extern "C" fn() -> ();
+impl UserOscHookTable {
+    /// The `func_*` fields in table order, without the prefix.
+    pub const HOOK_NAMES: [&'static str; 7] =
+        ["entry", "cycle", "on", "off", "mute", "value", "param"];
+}
 
 pub fn init_cb<T: UserOsc>(instance: &mut MaybeUninit<T>, platform: u32, api: u32) {
+    // Only the device loader leaves .bss and constructors to us; anywhere else
+    // the platform runtime has already done this.
+    #[cfg(target_os = "none")]
     unsafe {
-        let mut bss_p: *mut u8 = &mut _bss_start;
-        let bss_e: *mut u8 = &mut _bss_end;
-        while bss_p != bss_e {
-            *bss_p = 0;
-            bss_p = bss_p.offset(1);
-        }
-
-        let mut init_p: *const InitFn = __init_array_start;
-        let init_e: *const InitFn = __init_array_end;
-        while init_p != init_e {
-            if !init_p.is_null() {
-                (*init_p)()
-            }
-            init_p = init_p.offset(1);
-        }
+        crate::runtime::init();
     }
 
     instance.write(T::init(platform, api));
 }
 
-extern "C" {
-    static mut _bss_start: u8;
-    static mut _bss_end: u8;
-
-    static mut __init_array_start: *const InitFn;
-    static mut __init_array_end: *const InitFn;
-}
-
 pub trait UserOsc {
     const PLATFORM: Platform;
+    /// `Param1` onwards, as declared in the unit's manifest.
+    const PARAMS: &'static [ParamDesc] = &[];
 
     fn init(_platform: u32, _api: u32) -> Self;
     fn cycle(&mut self, _params: &UserOscParam, _buf: &mut [i32]) {}
@@ -266,3 +424,48 @@ pub fn param_cb<T: UserOsc>(instance: &mut MaybeUninit<T>, idx: u16, value: u16)
         instance.param(param, value);
     }
 }
+
+#[cfg(all(test, feature = "internal_luts"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn sine_follows_std() {
+        for i in 0..1000 {
+            let x = i as f32 / 1000.0;
+            let expected = (x * core::f32::consts::TAU).sin();
+            assert!((osc_sinf(x) - expected).abs() < 1e-3, "sin({x})");
+            let expected = (x * core::f32::consts::TAU).cos();
+            assert!((osc_cosf(x) - expected).abs() < 1e-3, "cos({x})");
+        }
+    }
+
+    #[test]
+    fn second_half_mirrors_first() {
+        for i in 1..500 {
+            let x = i as f32 / 1000.0;
+            for idx in [0.0, 2.5, 6.0] {
+                let (a, b) = (osc_bl2_sawf(x, idx), osc_bl2_sawf(1.0 - x, idx));
+                assert!((a + b).abs() < 1e-4, "saw({x}, {idx})");
+            }
+        }
+    }
+
+    #[test]
+    fn tables_follow_notes() {
+        let notes = unsafe { wt_saw_notes };
+        assert_eq!(osc_bl_saw_idx(0.0), 0.0);
+        assert_eq!(osc_bl_saw_idx(notes[0] as f32), 0.0);
+        assert_eq!(osc_bl_saw_idx(notes[3] as f32), 3.0);
+        let between = (notes[3] as f32 + notes[4] as f32) / 2.0;
+        assert_eq!(osc_bl_saw_idx(between), 3.5);
+        assert_eq!(osc_bl_saw_idx(127.0), 6.0);
+
+        // Whole indices play just that table
+        let x = 0.1;
+        assert_eq!(osc_bl2_sawf(x, 3.0), osc_bl_sawf(x, 3));
+        assert_eq!(osc_bl2_sawf(x, 6.0), osc_bl_sawf(x, 6));
+    }
+}
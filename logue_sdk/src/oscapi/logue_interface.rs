@@ -52,8 +52,8 @@ macro_rules! user_osc_hooks {
         #[link_section = ".hooks"]
         #[no_mangle]
         static hook_table: $crate::oscapi::UserOscHookTable = $crate::oscapi::UserOscHookTable {
-            magic: [b'U', b'O', b'S', b'C'],
-            api: 0x01_01_00,
+            magic: $crate::oscapi::HOOKS_MAGIC,
+            api: $crate::oscapi::API_VERSION,
             platform: <$osc as $crate::oscapi::UserOsc>::PLATFORM as u8,
             reserved0: [0, 0, 0, 0, 0, 0, 0],
             func_entry,
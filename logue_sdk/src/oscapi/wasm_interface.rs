@@ -1,6 +1,8 @@
+/// The functions a page calls to play `$osc`, which with `voices = N` is a
+/// `Poly` of `N` of them so that chords play.
 #[macro_export]
 macro_rules! user_osc_wasm_functions {
-    ($osc:ty) => {
+    (@osc $osc:ty, $voices:literal) => {
         static mut INSTANCE: core::mem::MaybeUninit<$osc> = core::mem::MaybeUninit::uninit();
 
         #[no_mangle]
@@ -10,49 +12,119 @@ macro_rules! user_osc_wasm_functions {
             }
         }
 
+        // The parameters passed to every hook, set from JS before calling it.
+        // Starts at middle C.
+        static mut PARAMS: $crate::oscapi::UserOscParam = $crate::oscapi::UserOscParam {
+            shape_lfo: 0,
+            pitch: 60 << 8,
+            cutoff: 0,
+            resonance: 0,
+            reserved0: [0; 3],
+        };
+
+        /// Note number in the high byte, fraction of a semitone in the low.
+        #[no_mangle]
+        extern "C" fn set_pitch(pitch: u16) {
+            unsafe {
+                PARAMS.pitch = pitch;
+            }
+        }
+
+        #[no_mangle]
+        extern "C" fn set_shape_lfo(shape_lfo: i32) {
+            unsafe {
+                PARAMS.shape_lfo = shape_lfo;
+            }
+        }
+
+        #[no_mangle]
+        extern "C" fn set_cutoff(cutoff: u16) {
+            unsafe {
+                PARAMS.cutoff = cutoff;
+            }
+        }
+
+        #[no_mangle]
+        extern "C" fn set_resonance(resonance: u16) {
+            unsafe {
+                PARAMS.resonance = resonance;
+            }
+        }
+
+        // The oscillator renders into this in firmware sized blocks, which are
+        // converted to float for JS, so `cycle` never allocates.
+        static mut SCRATCH: [i32; $crate::oscapi::MAX_FRAMES] = [0; $crate::oscapi::MAX_FRAMES];
+
         // This crate is no_std unless targeting wasm, so any code using std
         // has to be snuck through with a macro.
         #[no_mangle]
-        extern "C" fn cycle(
-            // params: *const $crate::oscapi::UserOscParam,
-            buf: *mut f32,
-            frames: i32,
-        ) {
+        extern "C" fn cycle(buf: *mut f32, frames: i32) {
             let osc = unsafe { INSTANCE.assume_init_mut() };
+            let params = unsafe { &*core::ptr::addr_of!(PARAMS) };
+            let scratch = unsafe { &mut *core::ptr::addr_of_mut!(SCRATCH) };
             let frames: usize = frames.try_into().unwrap();
 
-            // TODO: maybe use bindgen for creating osc params?
-            // TODO: what do upstream do?
-            let mut params = $crate::oscapi::UserOscParam::default();
-            params.pitch = 0x40_00;
-
-            let mut isamples: Vec<i32> = vec![0; frames];
-            <$osc as $crate::oscapi::UserOsc>::cycle(osc, &params, &mut isamples);
-
             let samples = unsafe { std::slice::from_raw_parts_mut(buf, frames) };
-            for i in 0..frames {
-                samples[i] = $crate::dsp::q31_to_f32(isamples[i])
+            for chunk in samples.chunks_mut($crate::oscapi::MAX_FRAMES) {
+                let q31 = &mut scratch[..chunk.len()];
+                <$osc as $crate::oscapi::UserOsc>::cycle(osc, params, q31);
+                for (y, x) in chunk.iter_mut().zip(q31.iter()) {
+                    *y = $crate::dsp::q31_to_f32(*x);
+                }
             }
         }
 
         #[no_mangle]
-        extern "C" fn on(params: *const $crate::oscapi::UserOscParam) {
+        extern "C" fn on() {
             unsafe {
-                $crate::oscapi::on_cb(&mut INSTANCE, params);
+                $crate::oscapi::on_cb(&mut INSTANCE, core::ptr::addr_of!(PARAMS));
             }
         }
 
         #[no_mangle]
-        extern "C" fn off(params: *const $crate::oscapi::UserOscParam) {
+        extern "C" fn off() {
+            unsafe {
+                $crate::oscapi::off_cb(&mut INSTANCE, core::ptr::addr_of!(PARAMS));
+            }
+        }
+
+        /// How many notes can play at once.
+        #[no_mangle]
+        extern "C" fn voices() -> u32 {
+            $voices
+        }
+
+        /// Start `note`, which stops the note playing unless there's a voice
+        /// for each. Velocity isn't part of the oscillator API, so it's up to
+        /// the page to apply.
+        #[no_mangle]
+        extern "C" fn note_on(note: u8, _velocity: u8) {
+            set_pitch(u16::from(note) << 8);
+            on();
+        }
+
+        /// Release the voice playing `note`. A single voice is released
+        /// whatever it's playing.
+        #[no_mangle]
+        extern "C" fn note_off(note: u8) {
+            let params = unsafe { &*core::ptr::addr_of!(PARAMS) };
+            // The pitch may have been bent since, so the note is given as is
+            let params = $crate::oscapi::UserOscParam {
+                shape_lfo: params.shape_lfo,
+                pitch: u16::from(note) << 8,
+                cutoff: params.cutoff,
+                resonance: params.resonance,
+                reserved0: params.reserved0,
+            };
             unsafe {
-                $crate::oscapi::off_cb(&mut INSTANCE, params);
+                $crate::oscapi::off_cb(&mut INSTANCE, &params);
             }
         }
 
         #[no_mangle]
-        extern "C" fn mute(params: *const $crate::oscapi::UserOscParam) {
+        extern "C" fn mute() {
             unsafe {
-                $crate::oscapi::mute_cb(&mut INSTANCE, params);
+                $crate::oscapi::mute_cb(&mut INSTANCE, core::ptr::addr_of!(PARAMS));
             }
         }
 
@@ -70,6 +142,8 @@ macro_rules! user_osc_wasm_functions {
             }
         }
 
+        /// A buffer for `cycle` to fill, to be allocated once up front and
+        /// freed with `free_sample_buffer`.
         #[no_mangle]
         pub extern "C" fn allocate_sample_buffer(capacity: usize) -> *mut f32 {
             let mut vec = Vec::with_capacity(capacity);
@@ -77,5 +151,19 @@ macro_rules! user_osc_wasm_functions {
             std::mem::forget(vec);
             bytes
         }
+
+        /// Free a buffer from `allocate_sample_buffer` of the same capacity.
+        #[no_mangle]
+        pub extern "C" fn free_sample_buffer(buf: *mut f32, capacity: usize) {
+            unsafe {
+                drop(Vec::from_raw_parts(buf, 0, capacity));
+            }
+        }
+    };
+    ($osc:ty, voices = $voices:literal) => {
+        $crate::user_osc_wasm_functions!(@osc $crate::poly::Poly<$osc, $voices>, $voices);
+    };
+    ($osc:ty) => {
+        $crate::user_osc_wasm_functions!(@osc $osc, 1);
     };
 }
@@ -0,0 +1,217 @@
+//! Chords from a monophonic oscillator, for hosts.
+//!
+//! On the device each `UserOsc` is a single voice. `Poly` runs `N` of them as
+//! one `UserOsc`, so the host renderer, given `--voices`, and the WASM page,
+//! given `voices = N` in `user_osc_wasm_functions!`, can play chords without
+//! any change to the unit. It's only built for the host and WASM.
+
+use crate::dsp::{f32_to_q31, q31_to_f32};
+use crate::manifest::ParamDesc;
+use crate::oscapi::{OscParam, Platform, UserOsc, UserOscParam, MAX_FRAMES};
+
+struct Voice<T> {
+    osc: T,
+    pitch: u16,
+    held: bool,
+    /// When the voice was last started or released, for picking which to
+    /// reuse.
+    age: u32,
+}
+
+impl<T> Voice<T> {
+    fn note(&self) -> u16 {
+        self.pitch >> 8
+    }
+}
+
+/// `N` voices of `T`. A note on starts the voice already playing that note,
+/// or else the one released longest ago, or else steals the one started
+/// longest ago. Released voices are silent, and the rest are summed without
+/// scaling, clipping at full scale.
+///
+/// Voices play at the pitch they were started with; `cycle` only takes the
+/// LFO, cutoff and resonance from its parameters. Note offs release the voice
+/// playing the note in the pitch they're given.
+pub struct Poly<T: UserOsc, const N: usize> {
+    voices: [Voice<T>; N],
+    clock: u32,
+}
+
+impl<T: UserOsc, const N: usize> Poly<T, N> {
+    fn tick(&mut self) -> u32 {
+        self.clock = self.clock.wrapping_add(1);
+        self.clock
+    }
+
+    fn allocate(&self, note: u16) -> usize {
+        let oldest = |held: bool| {
+            (0..N)
+                .filter(|&i| self.voices[i].held == held)
+                .min_by_key(|&i| self.voices[i].age)
+        };
+        (0..N)
+            .find(|&i| self.voices[i].held && self.voices[i].note() == note)
+            .or_else(|| oldest(false))
+            .or_else(|| oldest(true))
+            .unwrap()
+    }
+
+    /// The pitches of the voices playing, in voice order.
+    pub fn held(&self) -> impl Iterator<Item = u16> + '_ {
+        self.voices.iter().filter(|v| v.held).map(|v| v.pitch)
+    }
+}
+
+fn voice_params(params: &UserOscParam, pitch: u16) -> UserOscParam {
+    UserOscParam {
+        shape_lfo: params.shape_lfo,
+        pitch,
+        cutoff: params.cutoff,
+        resonance: params.resonance,
+        reserved0: params.reserved0,
+    }
+}
+
+impl<T: UserOsc, const N: usize> UserOsc for Poly<T, N> {
+    const PLATFORM: Platform = T::PLATFORM;
+    const PARAMS: &'static [ParamDesc] = T::PARAMS;
+
+    fn init(platform: u32, api: u32) -> Self {
+        assert!(N > 0, "a Poly needs at least one voice");
+        Self {
+            voices: core::array::from_fn(|_| Voice {
+                osc: T::init(platform, api),
+                pitch: 0,
+                held: false,
+                age: 0,
+            }),
+            clock: 0,
+        }
+    }
+
+    fn cycle(&mut self, params: &UserOscParam, buf: &mut [i32]) {
+        let mut scratch = [0; MAX_FRAMES];
+        let mut sum = [0.0; MAX_FRAMES];
+
+        for chunk in buf.chunks_mut(MAX_FRAMES) {
+            let sum = &mut sum[..chunk.len()];
+            sum.fill(0.0);
+            for voice in self.voices.iter_mut().filter(|v| v.held) {
+                let q31 = &mut scratch[..chunk.len()];
+                voice.osc.cycle(&voice_params(params, voice.pitch), q31);
+                for (y, x) in sum.iter_mut().zip(q31.iter()) {
+                    *y += q31_to_f32(*x);
+                }
+            }
+            for (y, x) in chunk.iter_mut().zip(sum.iter()) {
+                *y = f32_to_q31(x.clamp(-1.0, 1.0));
+            }
+        }
+    }
+
+    fn note_on(&mut self, params: &UserOscParam) {
+        let i = self.allocate(params.pitch >> 8);
+        let age = self.tick();
+        let voice = &mut self.voices[i];
+        voice.pitch = params.pitch;
+        voice.held = true;
+        voice.age = age;
+        voice.osc.note_on(&voice_params(params, voice.pitch));
+    }
+
+    fn note_off(&mut self, params: &UserOscParam) {
+        let note = params.pitch >> 8;
+        let Some(i) = (0..N).find(|&i| self.voices[i].held && self.voices[i].note() == note) else {
+            return;
+        };
+        let age = self.tick();
+        let voice = &mut self.voices[i];
+        voice.held = false;
+        voice.age = age;
+        voice.osc.note_off(&voice_params(params, voice.pitch));
+    }
+
+    fn mute(&mut self, params: &UserOscParam) {
+        for voice in &mut self.voices {
+            voice.held = false;
+            voice.osc.mute(&voice_params(params, voice.pitch));
+        }
+    }
+
+    fn value(&mut self, value: u16) {
+        for voice in &mut self.voices {
+            voice.osc.value(value);
+        }
+    }
+
+    fn param(&mut self, param: OscParam, value: u16) {
+        for voice in &mut self.voices {
+            voice.osc.param(param, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs its note number as a q31 fraction of 128.
+    struct Note(u16);
+
+    impl UserOsc for Note {
+        const PLATFORM: Platform = Platform::Prologue;
+
+        fn init(_platform: u32, _api: u32) -> Self {
+            Note(0)
+        }
+
+        fn cycle(&mut self, _params: &UserOscParam, buf: &mut [i32]) {
+            buf.fill(f32_to_q31(f32::from(self.0) / 128.0));
+        }
+
+        fn note_on(&mut self, params: &UserOscParam) {
+            self.0 = params.pitch >> 8;
+        }
+    }
+
+    fn on(poly: &mut Poly<Note, 2>, note: u16) {
+        poly.note_on(&voice_params(&UserOscParam::default(), note << 8));
+    }
+
+    fn off(poly: &mut Poly<Note, 2>, note: u16) {
+        poly.note_off(&voice_params(&UserOscParam::default(), note << 8));
+    }
+
+    fn notes(poly: &Poly<Note, 2>) -> [Option<u16>; 2] {
+        let mut held = poly.held().map(|p| p >> 8);
+        [held.next(), held.next()]
+    }
+
+    #[test]
+    fn steals_oldest_voice() {
+        let mut poly = Poly::<Note, 2>::init(0, 0);
+        on(&mut poly, 60);
+        on(&mut poly, 64);
+        on(&mut poly, 67);
+        assert_eq!(notes(&poly), [Some(67), Some(64)]);
+
+        // Released voices are reused before held ones are stolen
+        off(&mut poly, 67);
+        on(&mut poly, 72);
+        assert_eq!(notes(&poly), [Some(72), Some(64)]);
+    }
+
+    #[test]
+    fn sums_held_voices() {
+        let mut poly = Poly::<Note, 2>::init(0, 0);
+        on(&mut poly, 32);
+        on(&mut poly, 16);
+        let mut buf = [0; 100];
+        poly.cycle(&UserOscParam::default(), &mut buf);
+        assert!(buf.iter().all(|&x| (q31_to_f32(x) - 0.375).abs() < 1e-6));
+
+        off(&mut poly, 32);
+        poly.cycle(&UserOscParam::default(), &mut buf);
+        assert!(buf.iter().all(|&x| (q31_to_f32(x) - 0.125).abs() < 1e-6));
+    }
+}
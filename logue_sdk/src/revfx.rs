@@ -0,0 +1,12 @@
+mod logue_interface;
+
+crate::sdram_fx::sdram_fx! {
+    magic: b"URFX",
+    param: RevFxParam,
+    hook_table: UserRevFxHookTable,
+    /// A reverb effect. Audio is processed in place, as interleaved stereo.
+    ///
+    /// Reverb buffers should be allocated from `sdram` in `init`, and kept in the
+    /// returned state.
+    trait UserRevFx;
+}
@@ -0,0 +1,6 @@
+#[macro_export]
+macro_rules! user_revfx_hooks {
+    ($fx:ty) => {
+        $crate::__sdram_fx_hooks!(revfx, UserRevFxHookTable, UserRevFx, $fx);
+    };
+}
@@ -0,0 +1,31 @@
+use core::ptr::addr_of_mut;
+
+type InitFn = extern "C" fn() -> ();
+
+/// Clear .bss and run static constructors, which the device loader leaves to
+/// the unit's entry hook.
+pub(crate) unsafe fn init() {
+    let mut bss_p: *mut u8 = addr_of_mut!(_bss_start);
+    let bss_e: *mut u8 = addr_of_mut!(_bss_end);
+    while bss_p != bss_e {
+        *bss_p = 0;
+        bss_p = bss_p.offset(1);
+    }
+
+    let mut init_p: *const InitFn = __init_array_start;
+    let init_e: *const InitFn = __init_array_end;
+    while init_p != init_e {
+        if !init_p.is_null() {
+            (*init_p)()
+        }
+        init_p = init_p.offset(1);
+    }
+}
+
+extern "C" {
+    static mut _bss_start: u8;
+    static mut _bss_end: u8;
+
+    static mut __init_array_start: *const InitFn;
+    static mut __init_array_end: *const InitFn;
+}
@@ -0,0 +1,105 @@
+//! Delay and reverb units get a region of external SDRAM for their buffers,
+//! which is far larger than the SRAM the unit itself is loaded into.
+//!
+//! Buffers are handed out once, by the allocator passed to the unit's `init`,
+//! and live until the unit is unloaded, so they can be kept in the unit's
+//! state and borrowed from `process`.
+
+use core::mem;
+use core::slice;
+
+/// Size of the SDRAM region available to a unit, matching the linker scripts.
+pub const SDRAM_SIZE: usize = 3 * 1024 * 1024;
+
+pub struct SdramAllocator {
+    next: usize,
+    end: usize,
+}
+
+impl SdramAllocator {
+    /// Start handing out the unit's SDRAM region. On the device the region is
+    /// only handed out once, as allocations last as long as the unit is
+    /// loaded; an allocator made by a later init has nothing to give.
+    pub(crate) fn new() -> Self {
+        let (start, end) = region();
+        Self { next: start, end }
+    }
+
+    /// Bytes left in the region.
+    pub fn available(&self) -> usize {
+        self.end - self.next
+    }
+
+    /// Allocate `len` elements, each set to `value`, or `None` if the region
+    /// doesn't have enough space left.
+    pub fn alloc_slice<T: Copy>(&mut self, len: usize, value: T) -> Option<&'static mut [T]> {
+        let start = self.next.checked_next_multiple_of(mem::align_of::<T>())?;
+        let end = start.checked_add(len.checked_mul(mem::size_of::<T>())?)?;
+        if end > self.end {
+            return None;
+        }
+        self.next = end;
+
+        let buf = unsafe { slice::from_raw_parts_mut(start as *mut T, len) };
+        buf.fill(value);
+        Some(buf)
+    }
+}
+
+#[cfg(not(feature = "host"))]
+fn region() -> (usize, usize) {
+    use core::ptr::addr_of;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    extern "C" {
+        static _sdram_heap_start: u8;
+        static _sdram_heap_end: u8;
+    }
+
+    // Initialised to true so it lives in .data, which unlike .bss isn't
+    // cleared again when the unit is initialised again
+    static UNCLAIMED: AtomicBool = AtomicBool::new(true);
+
+    let start = addr_of!(_sdram_heap_start) as usize;
+    let end = addr_of!(_sdram_heap_end) as usize;
+    if UNCLAIMED.swap(false, Ordering::Relaxed) {
+        (start, end)
+    } else {
+        (end, end)
+    }
+}
+
+#[cfg(feature = "host")]
+fn region() -> (usize, usize) {
+    crate::host::sdram_region()
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_are_aligned() {
+        let mut sdram = SdramAllocator::new();
+        let bytes = sdram.alloc_slice(3, 1u8).unwrap();
+        let floats = sdram.alloc_slice(5, 2.0f64).unwrap();
+        assert_eq!(floats.as_ptr() as usize % mem::align_of::<f64>(), 0);
+        assert!(floats.as_ptr() as usize >= bytes.as_ptr() as usize + 3);
+        assert_eq!((bytes[0], floats[4]), (1, 2.0));
+        assert_eq!(sdram.available(), SDRAM_SIZE - 8 - 5 * 8);
+    }
+
+    #[test]
+    fn exhaustion_is_none() {
+        let mut sdram = SdramAllocator::new();
+        assert!(sdram.alloc_slice(SDRAM_SIZE / 4 + 1, 0.0f32).is_none());
+        assert!(sdram.alloc_slice(usize::MAX, 0.0f32).is_none());
+
+        // Failures don't use any of the region
+        let all = sdram.alloc_slice(SDRAM_SIZE / 4, 0.0f32).unwrap();
+        assert_eq!(all.len(), SDRAM_SIZE / 4);
+        assert_eq!(sdram.available(), 0);
+        assert!(sdram.alloc_slice(1, 0u8).is_none());
+        assert!(sdram.alloc_slice(0, 0u8).is_some());
+    }
+}
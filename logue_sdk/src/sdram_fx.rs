@@ -0,0 +1,176 @@
+//! What delay and reverb effects have in common. Both get the same hooks and
+//! parameters, and SDRAM for their buffers, and the firmware tells them apart
+//! by the magic in their hook table. Each module instantiates `sdram_fx!` with
+//! its own names, and its hooks macro forwards to `__sdram_fx_hooks!`.
+
+/// The parameter enum, hook table, trait and callbacks of an SDRAM effect
+/// module.
+macro_rules! sdram_fx {
+    (
+        magic: $magic:literal,
+        param: $param:ident,
+        hook_table: $table:ident,
+        $(#[$trait_meta:meta])*
+        trait $trait:ident;
+    ) => {
+        use core::mem::{transmute, MaybeUninit};
+        use core::slice;
+
+        use $crate::oscapi::Platform;
+        use $crate::sdram::SdramAllocator;
+
+        pub const HOOKS_MAGIC: [u8; 4] = *$magic;
+
+        #[repr(u8)]
+        pub enum $param {
+            ParamTime = 0,
+            ParamDepth,
+            ParamShiftDepth,
+        }
+
+        impl TryFrom<u8> for $param {
+            type Error = ();
+
+            fn try_from(x: u8) -> Result<$param, Self::Error> {
+                if x > $param::ParamShiftDepth as u8 {
+                    Err(())
+                } else {
+                    Ok(unsafe { transmute::<u8, $param>(x) })
+                }
+            }
+        }
+
+        type FuncEntry = extern "C" fn(platform: u32, api: u32) -> ();
+        type FuncProcess = extern "C" fn(xn: *mut f32, frames: u32);
+        type FuncSuspend = extern "C" fn();
+        type FuncResume = extern "C" fn();
+        type FuncParam = extern "C" fn(index: u8, value: i32);
+
+        #[repr(C, packed(1))]
+        pub struct $table {
+            pub magic: [u8; 4],
+            pub api: u32,
+            pub platform: u8,
+            pub reserved0: [u8; 7],
+            pub func_entry: FuncEntry,
+            pub func_process: FuncProcess,
+            pub func_suspend: FuncSuspend,
+            pub func_resume: FuncResume,
+            pub func_param: FuncParam,
+        }
+
+        impl $table {
+            /// The `func_*` fields in table order, without the prefix.
+            pub const HOOK_NAMES: [&'static str; 5] =
+                ["entry", "process", "suspend", "resume", "param"];
+        }
+
+        $(#[$trait_meta])*
+        pub trait $trait {
+            const PLATFORM: Platform;
+
+            fn init(_platform: u32, _api: u32, _sdram: &mut SdramAllocator) -> Self;
+            fn process(&mut self, _xn: &mut [f32]) {}
+            fn suspend(&mut self) {}
+            fn resume(&mut self) {}
+            /// `value` is a q31 fraction of the knob range.
+            fn param(&mut self, _param: $param, _value: i32) {}
+        }
+
+        pub fn init_cb<T: $trait>(instance: &mut MaybeUninit<T>, platform: u32, api: u32) {
+            #[cfg(target_os = "none")]
+            unsafe {
+                $crate::runtime::init();
+            }
+
+            let mut sdram = SdramAllocator::new();
+            instance.write(T::init(platform, api, &mut sdram));
+        }
+
+        /// # Safety
+        /// `instance` must have been initialised by `init_cb`, and `xn` must be
+        /// valid for `frames` stereo frames.
+        pub unsafe fn process_cb<T: $trait>(
+            instance: &mut MaybeUninit<T>,
+            xn: *mut f32,
+            frames: u32,
+        ) {
+            let xn = slice::from_raw_parts_mut(xn, frames as usize * 2);
+            let instance = instance.assume_init_mut();
+            instance.process(xn);
+        }
+
+        pub fn suspend_cb<T: $trait>(instance: &mut MaybeUninit<T>) {
+            let instance = unsafe { instance.assume_init_mut() };
+            instance.suspend();
+        }
+
+        pub fn resume_cb<T: $trait>(instance: &mut MaybeUninit<T>) {
+            let instance = unsafe { instance.assume_init_mut() };
+            instance.resume();
+        }
+
+        pub fn param_cb<T: $trait>(instance: &mut MaybeUninit<T>, index: u8, value: i32) {
+            if let Ok(param) = index.try_into() {
+                let instance = unsafe { instance.assume_init_mut() };
+                instance.param(param, value);
+            }
+        }
+    };
+}
+
+pub(crate) use sdram_fx;
+
+/// The hooks and hook table of `$fx`, an effect of the SDRAM effect module
+/// `$module`. Use `user_delfx_hooks!` or `user_revfx_hooks!` instead.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __sdram_fx_hooks {
+    ($module:ident, $table:ident, $trait:ident, $fx:ty) => {
+        static mut INSTANCE: core::mem::MaybeUninit<$fx> = core::mem::MaybeUninit::uninit();
+
+        extern "C" fn func_entry(platform: u32, api: u32) {
+            unsafe {
+                $crate::$module::init_cb(&mut *core::ptr::addr_of_mut!(INSTANCE), platform, api);
+            }
+        }
+
+        extern "C" fn func_process(xn: *mut f32, frames: u32) {
+            unsafe {
+                $crate::$module::process_cb(&mut *core::ptr::addr_of_mut!(INSTANCE), xn, frames);
+            }
+        }
+
+        extern "C" fn func_suspend() {
+            unsafe {
+                $crate::$module::suspend_cb(&mut *core::ptr::addr_of_mut!(INSTANCE));
+            }
+        }
+
+        extern "C" fn func_resume() {
+            unsafe {
+                $crate::$module::resume_cb(&mut *core::ptr::addr_of_mut!(INSTANCE));
+            }
+        }
+
+        extern "C" fn func_param(index: u8, value: i32) {
+            unsafe {
+                $crate::$module::param_cb(&mut *core::ptr::addr_of_mut!(INSTANCE), index, value);
+            }
+        }
+
+        #[link_section = ".hooks"]
+        #[no_mangle]
+        static hook_table: $crate::$module::$table = $crate::$module::$table {
+            magic: $crate::$module::HOOKS_MAGIC,
+            api: $crate::oscapi::API_VERSION,
+            platform: <$fx as $crate::$module::$trait>::PLATFORM as u8,
+            reserved0: [0, 0, 0, 0, 0, 0, 0],
+            func_entry,
+            func_process,
+            func_suspend,
+            func_resume,
+            func_param,
+        };
+    };
+}
@@ -0,0 +1,598 @@
+//! logue SDK v2 units. Rather than a hook table at a fixed address, a unit
+//! provides a `unit_header` descriptor and a set of `unit_*` callbacks that
+//! the firmware looks up by name.
+//!
+//! The header and runtime layouts differ between platforms, so one of the
+//! `nts1_mkii`, `minilogue_xd` or `drumlogue` features must be enabled.
+
+use core::ffi::{c_char, c_void, CStr};
+use core::mem;
+use core::slice;
+
+mod logue_interface;
+
+#[cfg(any(
+    all(feature = "nts1_mkii", feature = "minilogue_xd"),
+    all(feature = "nts1_mkii", feature = "drumlogue"),
+    all(feature = "minilogue_xd", feature = "drumlogue"),
+))]
+compile_error!("only one v2 unit platform feature may be enabled");
+
+pub const API_VERSION: u32 = 0x02_00_00;
+
+const API_MAJOR_MASK: u32 = 0x7F << 16;
+const API_MINOR_MASK: u32 = 0x7F << 8;
+
+pub const TARGET_PROLOGUE: u16 = 1 << 8;
+pub const TARGET_MINILOGUE_XD: u16 = 2 << 8;
+pub const TARGET_NUTEKT_DIGITAL: u16 = 3 << 8;
+pub const TARGET_DRUMLOGUE: u16 = 4 << 8;
+pub const TARGET_NTS1_MKII: u16 = 5 << 8;
+
+pub const TARGET_PLATFORM_MASK: u16 = 0x7F << 8;
+pub const TARGET_MODULE_MASK: u16 = 0x7F;
+
+#[cfg(feature = "nts1_mkii")]
+mod platform {
+    pub const TARGET_PLATFORM: u16 = super::TARGET_NTS1_MKII;
+    pub const NAME_LEN: usize = 13;
+    pub const PARAM_NAME_LEN: usize = 12;
+    pub const MAX_PARAM_COUNT: usize = 10;
+}
+
+#[cfg(feature = "minilogue_xd")]
+mod platform {
+    pub const TARGET_PLATFORM: u16 = super::TARGET_MINILOGUE_XD;
+    pub const NAME_LEN: usize = 13;
+    pub const PARAM_NAME_LEN: usize = 12;
+    pub const MAX_PARAM_COUNT: usize = 10;
+}
+
+#[cfg(feature = "drumlogue")]
+mod platform {
+    pub const TARGET_PLATFORM: u16 = super::TARGET_DRUMLOGUE;
+    pub const NAME_LEN: usize = 13;
+    pub const PARAM_NAME_LEN: usize = 12;
+    pub const MAX_PARAM_COUNT: usize = 24;
+}
+
+pub use platform::{MAX_PARAM_COUNT, NAME_LEN, PARAM_NAME_LEN, TARGET_PLATFORM};
+
+#[repr(u16)]
+#[derive(Clone, Copy)]
+pub enum UnitModule {
+    Global = 0,
+    ModFx,
+    DelFx,
+    RevFx,
+    Osc,
+    Synth,
+    MasterFx,
+}
+
+#[repr(i8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitError {
+    Target = -1,
+    ApiVersion = -2,
+    Samplerate = -4,
+    Geometry = -8,
+    Memory = -16,
+    Undef = -32,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum ParamType {
+    None = 0,
+    Percent,
+    Db,
+    Cents,
+    Semi,
+    Oct,
+    Hertz,
+    KHertz,
+    Bpm,
+    Msec,
+    Sec,
+    Enum,
+    Strings,
+    Bitmaps,
+    DryWet,
+    Pan,
+    Spread,
+    OnOff,
+    MidiNote,
+}
+
+const fn c_name<const N: usize>(name: &str) -> [u8; N] {
+    let bytes = name.as_bytes();
+    assert!(bytes.len() < N, "name is too long");
+
+    let mut out = [0; N];
+    let mut i = 0;
+    while i < bytes.len() {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+#[repr(C, packed(1))]
+#[derive(Clone, Copy)]
+pub struct UnitParam {
+    pub min: i16,
+    pub max: i16,
+    pub center: i16,
+    pub init: i16,
+    pub param_type: u8,
+    /// Bits 0-3: fractional digits, bit 4: fraction is decimal rather than
+    /// binary fixed point.
+    pub frac: u8,
+    pub name: [u8; PARAM_NAME_LEN + 1],
+}
+
+impl UnitParam {
+    pub const NONE: UnitParam = UnitParam::new("", 0, 0, 0, 0, ParamType::None);
+
+    pub const fn new(
+        name: &str,
+        min: i16,
+        max: i16,
+        center: i16,
+        init: i16,
+        param_type: ParamType,
+    ) -> Self {
+        Self {
+            min,
+            max,
+            center,
+            init,
+            param_type: param_type as u8,
+            frac: 0,
+            name: c_name(name),
+        }
+    }
+
+    /// Display the value with `digits` fractional digits, either as decimal
+    /// places or as binary fixed point.
+    pub const fn frac(mut self, digits: u8, decimal: bool) -> Self {
+        assert!(digits < 16, "too many fractional digits");
+        self.frac = digits | (decimal as u8) << 4;
+        self
+    }
+}
+
+#[repr(C, packed(1))]
+pub struct UnitHeader {
+    pub header_size: u32,
+    pub target: u16,
+    pub api: u32,
+    pub dev_id: u32,
+    pub unit_id: u32,
+    pub version: u32,
+    pub name: [u8; NAME_LEN + 1],
+    pub num_presets: u32,
+    pub num_params: u32,
+    pub params: [UnitParam; MAX_PARAM_COUNT],
+}
+
+impl UnitHeader {
+    /// `version` is packed as major, minor and patch bytes, like `api`.
+    pub const fn new(
+        module: UnitModule,
+        dev_id: u32,
+        unit_id: u32,
+        version: u32,
+        name: &str,
+        params: &[UnitParam],
+    ) -> Self {
+        assert!(params.len() <= MAX_PARAM_COUNT, "too many parameters");
+
+        let mut all_params = [UnitParam::NONE; MAX_PARAM_COUNT];
+        let mut i = 0;
+        while i < params.len() {
+            all_params[i] = params[i];
+            i += 1;
+        }
+
+        Self {
+            header_size: mem::size_of::<Self>() as u32,
+            target: TARGET_PLATFORM | module as u16,
+            api: API_VERSION,
+            dev_id,
+            unit_id,
+            version,
+            name: c_name(name),
+            num_presets: 0,
+            num_params: params.len() as u32,
+            params: all_params,
+        }
+    }
+
+    pub const fn presets(mut self, num_presets: u32) -> Self {
+        self.num_presets = num_presets;
+        self
+    }
+}
+
+#[cfg(any(feature = "nts1_mkii", feature = "minilogue_xd"))]
+#[repr(C, packed(1))]
+pub struct UnitRuntimeHooks {
+    pub runtime_context: *mut c_void,
+    pub sdram_alloc: Option<extern "C" fn(size: usize) -> *mut u8>,
+    pub sdram_free: Option<extern "C" fn(mem: *const u8)>,
+    pub sdram_avail: Option<extern "C" fn() -> usize>,
+}
+
+#[cfg(feature = "drumlogue")]
+pub const SAMPLE_NAME_LEN: usize = 31;
+
+#[cfg(feature = "drumlogue")]
+#[repr(C, packed(1))]
+pub struct SampleWrapper {
+    pub bank: u8,
+    pub index: u8,
+    pub channels: u8,
+    pub padding: u8,
+    pub name: [u8; SAMPLE_NAME_LEN + 1],
+    pub frames: usize,
+    pub sample_ptr: *const f32,
+}
+
+#[cfg(feature = "drumlogue")]
+#[repr(C, packed(1))]
+pub struct UnitRuntimeHooks {
+    pub runtime_context: *mut c_void,
+    pub get_num_sample_banks: Option<extern "C" fn() -> u8>,
+    pub get_num_samples_for_bank: Option<extern "C" fn(bank: u8) -> u8>,
+    pub get_sample: Option<extern "C" fn(bank: u8, index: u8) -> *const SampleWrapper>,
+}
+
+#[repr(C, packed(1))]
+pub struct UnitRuntimeDesc {
+    pub target: u16,
+    pub api: u32,
+    pub samplerate: u32,
+    pub frames_per_buffer: u16,
+    pub input_channels: u8,
+    pub output_channels: u8,
+    pub hooks: UnitRuntimeHooks,
+}
+
+/// Whether a unit built against `API_VERSION` can be loaded by a runtime
+/// providing `api`.
+pub const fn api_is_compatible(api: u32) -> bool {
+    const MASK: u32 = API_MAJOR_MASK | API_MINOR_MASK;
+    api & API_MAJOR_MASK == API_VERSION & API_MAJOR_MASK && api & MASK <= API_VERSION & MASK
+}
+
+/// A v2 unit. Buffers passed to `render` are interleaved, with the channel
+/// counts given in the runtime descriptor at `init`.
+pub trait Unit: Sized {
+    const HEADER: UnitHeader;
+
+    fn init(desc: &UnitRuntimeDesc) -> Result<Self, UnitError>;
+    fn teardown(&mut self) {}
+    fn reset(&mut self) {}
+    fn resume(&mut self) {}
+    fn suspend(&mut self) {}
+    fn render(&mut self, _input: &[f32], _output: &mut [f32], _frames: usize) {}
+
+    fn get_preset_index(&self) -> u8 {
+        0
+    }
+    fn get_preset_name(&self, _idx: u8) -> Option<&CStr> {
+        None
+    }
+    fn load_preset(&mut self, _idx: u8) {}
+
+    fn get_param_value(&self, _id: u8) -> i32 {
+        0
+    }
+    fn get_param_str_value(&self, _id: u8, _value: i32) -> Option<&CStr> {
+        None
+    }
+    fn set_param_value(&mut self, _id: u8, _value: i32) {}
+    fn set_tempo(&mut self, _tempo: u32) {}
+    fn tempo_4ppqn_tick(&mut self, _counter: u32) {}
+
+    fn note_on(&mut self, _note: u8, _velocity: u8) {}
+    fn note_off(&mut self, _note: u8) {}
+    fn gate_on(&mut self, _velocity: u8) {}
+    fn gate_off(&mut self) {}
+    fn all_note_off(&mut self) {}
+    fn pitch_bend(&mut self, _bend: u16) {}
+    fn channel_pressure(&mut self, _pressure: u8) {}
+    fn aftertouch(&mut self, _note: u8, _aftertouch: u8) {}
+}
+
+/// Rendering whole frames rather than interleaved samples, for units with a
+/// fixed channel layout such as drumlogue's stereo synths and effects. Check
+/// the layout with `check_geometry` in `Unit::init`, and forward
+/// `Unit::render` to `render_frames`.
+pub trait RenderFrames<const IN: usize, const OUT: usize> {
+    fn render_frames(&mut self, input: &[[f32; IN]], output: &mut [[f32; OUT]]);
+}
+
+pub fn check_geometry<const IN: usize, const OUT: usize>(
+    desc: &UnitRuntimeDesc,
+) -> Result<(), UnitError> {
+    if desc.input_channels as usize != IN || desc.output_channels as usize != OUT {
+        return Err(UnitError::Geometry);
+    }
+    Ok(())
+}
+
+fn frame_count<const N: usize>(samples: usize, frames: usize) -> usize {
+    samples.checked_div(N).map_or(0, |n| frames.min(n))
+}
+
+/// Split interleaved buffers into frames and render them with `unit`. A unit
+/// without inputs gets an empty input slice.
+pub fn render_frames<const IN: usize, const OUT: usize, T: RenderFrames<IN, OUT>>(
+    unit: &mut T,
+    input: &[f32],
+    output: &mut [f32],
+    frames: usize,
+) {
+    let in_frames = frame_count::<IN>(input.len(), frames);
+    let out_frames = frame_count::<OUT>(output.len(), frames);
+    unsafe {
+        let input = slice::from_raw_parts(input.as_ptr().cast::<[f32; IN]>(), in_frames);
+        let output =
+            slice::from_raw_parts_mut(output.as_mut_ptr().cast::<[f32; OUT]>(), out_frames);
+        unit.render_frames(input, output);
+    }
+}
+
+/// A unit along with the buffer geometry it was initialised with.
+pub struct Instance<T: Unit> {
+    unit: T,
+    input_channels: usize,
+    output_channels: usize,
+}
+
+impl<T: Unit> Instance<T> {
+    pub fn unit(&mut self) -> &mut T {
+        &mut self.unit
+    }
+}
+
+/// Run `f` on the unit, or return the default if it isn't initialised.
+pub fn with_unit<T: Unit, R: Default>(
+    instance: &mut Option<Instance<T>>,
+    f: impl FnOnce(&mut T) -> R,
+) -> R {
+    instance
+        .as_mut()
+        .map_or_else(R::default, |i| f(&mut i.unit))
+}
+
+fn c_str_ptr(s: Option<&CStr>) -> *const c_char {
+    s.map_or(core::ptr::null(), CStr::as_ptr)
+}
+
+/// # Safety
+/// `desc` must be null or point to a valid runtime descriptor.
+pub unsafe fn init_cb<T: Unit>(
+    instance: &mut Option<Instance<T>>,
+    desc: *const UnitRuntimeDesc,
+) -> i8 {
+    *instance = None;
+
+    let Some(desc) = desc.as_ref() else {
+        return UnitError::Undef as i8;
+    };
+
+    if desc.target != T::HEADER.target {
+        return UnitError::Target as i8;
+    }
+    if !api_is_compatible(desc.api) {
+        return UnitError::ApiVersion as i8;
+    }
+
+    match T::init(desc) {
+        Ok(unit) => {
+            *instance = Some(Instance {
+                unit,
+                input_channels: desc.input_channels as usize,
+                output_channels: desc.output_channels as usize,
+            });
+            0
+        }
+        Err(e) => e as i8,
+    }
+}
+
+pub fn teardown_cb<T: Unit>(instance: &mut Option<Instance<T>>) {
+    if let Some(mut instance) = instance.take() {
+        instance.unit.teardown();
+    }
+}
+
+/// # Safety
+/// `input` and `output` must each be null or valid for `frames` frames of the
+/// channel counts the unit was initialised with, and must not overlap.
+pub unsafe fn render_cb<T: Unit>(
+    instance: &mut Option<Instance<T>>,
+    input: *const f32,
+    output: *mut f32,
+    frames: u32,
+) {
+    let Some(instance) = instance else {
+        return;
+    };
+
+    let frames = frames as usize;
+    let input = if input.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(input, frames * instance.input_channels)
+    };
+    let output = if output.is_null() {
+        &mut []
+    } else {
+        slice::from_raw_parts_mut(output, frames * instance.output_channels)
+    };
+    instance.unit.render(input, output, frames);
+}
+
+pub fn get_preset_name_cb<T: Unit>(instance: &mut Option<Instance<T>>, idx: u8) -> *const c_char {
+    c_str_ptr(instance.as_ref().and_then(|i| i.unit.get_preset_name(idx)))
+}
+
+pub fn get_param_str_value_cb<T: Unit>(
+    instance: &mut Option<Instance<T>>,
+    id: u8,
+    value: i32,
+) -> *const c_char {
+    c_str_ptr(
+        instance
+            .as_ref()
+            .and_then(|i| i.unit.get_param_str_value(id, value)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ptr;
+
+    use super::*;
+
+    /// A stereo synth that renders each frame as its index in the buffer.
+    struct Synth;
+
+    impl Unit for Synth {
+        const HEADER: UnitHeader = UnitHeader::new(
+            UnitModule::Synth,
+            0x1234,
+            1,
+            0x01_00_00,
+            "Synth",
+            &[UnitParam::new("Drive", 0, 100, 0, 50, ParamType::Percent)],
+        );
+
+        fn init(desc: &UnitRuntimeDesc) -> Result<Self, UnitError> {
+            check_geometry::<0, 2>(desc)?;
+            Ok(Synth)
+        }
+
+        fn render(&mut self, input: &[f32], output: &mut [f32], frames: usize) {
+            render_frames(self, input, output, frames);
+        }
+    }
+
+    impl RenderFrames<0, 2> for Synth {
+        fn render_frames(&mut self, input: &[[f32; 0]], output: &mut [[f32; 2]]) {
+            assert!(input.is_empty());
+            for (i, frame) in output.iter_mut().enumerate() {
+                *frame = [i as f32, -(i as f32)];
+            }
+        }
+    }
+
+    #[cfg(any(feature = "nts1_mkii", feature = "minilogue_xd"))]
+    const HOOKS: UnitRuntimeHooks = UnitRuntimeHooks {
+        runtime_context: ptr::null_mut(),
+        sdram_alloc: None,
+        sdram_free: None,
+        sdram_avail: None,
+    };
+
+    #[cfg(feature = "drumlogue")]
+    const HOOKS: UnitRuntimeHooks = UnitRuntimeHooks {
+        runtime_context: ptr::null_mut(),
+        get_num_sample_banks: None,
+        get_num_samples_for_bank: None,
+        get_sample: None,
+    };
+
+    fn desc(target: u16, api: u32, outputs: u8) -> UnitRuntimeDesc {
+        UnitRuntimeDesc {
+            target,
+            api,
+            samplerate: 48_000,
+            frames_per_buffer: 64,
+            input_channels: 0,
+            output_channels: outputs,
+            hooks: HOOKS,
+        }
+    }
+
+    fn init(desc: &UnitRuntimeDesc) -> (i8, Option<Instance<Synth>>) {
+        let mut instance = None;
+        let result = unsafe { init_cb(&mut instance, desc) };
+        (result, instance)
+    }
+
+    const TARGET: u16 = TARGET_PLATFORM | UnitModule::Synth as u16;
+
+    #[test]
+    fn api_compatibility() {
+        assert!(api_is_compatible(API_VERSION));
+        // Patch releases don't change the interface
+        assert!(api_is_compatible(API_VERSION | 0x05));
+        assert!(!api_is_compatible(API_VERSION + (1 << 8)));
+        assert!(!api_is_compatible(API_VERSION + (1 << 16)));
+        assert!(!api_is_compatible(API_VERSION - (1 << 16)));
+    }
+
+    #[test]
+    fn init_checks_runtime() {
+        assert_eq!({ Synth::HEADER.target }, TARGET);
+
+        let (result, instance) = init(&desc(TARGET, API_VERSION, 2));
+        assert_eq!(result, 0);
+        assert!(instance.is_some());
+
+        let other = TARGET_PROLOGUE | UnitModule::Synth as u16;
+        let (result, instance) = init(&desc(other, API_VERSION, 2));
+        assert_eq!(result, UnitError::Target as i8);
+        assert!(instance.is_none());
+        let osc = TARGET_PLATFORM | UnitModule::Osc as u16;
+        assert_eq!(init(&desc(osc, API_VERSION, 2)).0, UnitError::Target as i8);
+
+        let newer = API_VERSION + (1 << 16);
+        assert_eq!(init(&desc(TARGET, newer, 2)).0, UnitError::ApiVersion as i8);
+        assert_eq!(
+            init(&desc(TARGET, API_VERSION, 1)).0,
+            UnitError::Geometry as i8
+        );
+
+        let mut instance = None;
+        let result = unsafe { init_cb::<Synth>(&mut instance, ptr::null()) };
+        assert_eq!(result, UnitError::Undef as i8);
+    }
+
+    #[test]
+    fn names_are_nul_terminated() {
+        assert_eq!(c_name::<6>("abc"), *b"abc\0\0\0");
+        assert_eq!(&Synth::HEADER.name[..6], b"Synth\0");
+        let param = Synth::HEADER.params[0];
+        assert_eq!(&param.name[..6], b"Drive\0");
+        assert_eq!({ Synth::HEADER.num_params }, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "name is too long")]
+    fn long_names_are_rejected() {
+        c_name::<4>("abcd");
+    }
+
+    #[test]
+    fn renders_whole_frames() {
+        let (_, mut instance) = init(&desc(TARGET, API_VERSION, 2));
+        let mut out = [9.0; 8];
+        unsafe { render_cb(&mut instance, ptr::null(), out.as_mut_ptr(), 4) };
+        assert_eq!(out, [0.0, -0.0, 1.0, -1.0, 2.0, -2.0, 3.0, -3.0]);
+
+        // Never more frames than the buffers hold, and no partial frames
+        let mut out = [9.0; 7];
+        render_frames(&mut Synth, &[], &mut out, 4);
+        assert_eq!(out, [0.0, -0.0, 1.0, -1.0, 2.0, -2.0, 9.0]);
+        let mut out = [9.0; 4];
+        render_frames(&mut Synth, &[], &mut out, 1);
+        assert_eq!(out, [0.0, -0.0, 9.0, 9.0]);
+    }
+}
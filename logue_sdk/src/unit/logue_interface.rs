@@ -0,0 +1,125 @@
+#[macro_export]
+macro_rules! unit_hooks {
+    ($unit:ty) => {
+        static mut INSTANCE: Option<$crate::unit::Instance<$unit>> = None;
+
+        #[link_section = ".unit_header"]
+        #[no_mangle]
+        #[used]
+        pub static unit_header: $crate::unit::UnitHeader = <$unit as $crate::unit::Unit>::HEADER;
+
+        fn instance() -> &'static mut Option<$crate::unit::Instance<$unit>> {
+            unsafe { &mut *core::ptr::addr_of_mut!(INSTANCE) }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_init(desc: *const $crate::unit::UnitRuntimeDesc) -> i8 {
+            unsafe { $crate::unit::init_cb(instance(), desc) }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_teardown() {
+            $crate::unit::teardown_cb(instance());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_reset() {
+            $crate::unit::with_unit(instance(), |u| u.reset());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_resume() {
+            $crate::unit::with_unit(instance(), |u| u.resume());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_suspend() {
+            $crate::unit::with_unit(instance(), |u| u.suspend());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_render(input: *const f32, output: *mut f32, frames: u32) {
+            unsafe { $crate::unit::render_cb(instance(), input, output, frames) };
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_get_preset_index() -> u8 {
+            $crate::unit::with_unit(instance(), |u| u.get_preset_index())
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_get_preset_name(idx: u8) -> *const core::ffi::c_char {
+            $crate::unit::get_preset_name_cb(instance(), idx)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_load_preset(idx: u8) {
+            $crate::unit::with_unit(instance(), |u| u.load_preset(idx));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_get_param_value(id: u8) -> i32 {
+            $crate::unit::with_unit(instance(), |u| u.get_param_value(id))
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_get_param_str_value(id: u8, value: i32) -> *const core::ffi::c_char {
+            $crate::unit::get_param_str_value_cb(instance(), id, value)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_set_param_value(id: u8, value: i32) {
+            $crate::unit::with_unit(instance(), |u| u.set_param_value(id, value));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_set_tempo(tempo: u32) {
+            $crate::unit::with_unit(instance(), |u| u.set_tempo(tempo));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_tempo_4ppqn_tick(counter: u32) {
+            $crate::unit::with_unit(instance(), |u| u.tempo_4ppqn_tick(counter));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_note_on(note: u8, velocity: u8) {
+            $crate::unit::with_unit(instance(), |u| u.note_on(note, velocity));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_note_off(note: u8) {
+            $crate::unit::with_unit(instance(), |u| u.note_off(note));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_gate_on(velocity: u8) {
+            $crate::unit::with_unit(instance(), |u| u.gate_on(velocity));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_gate_off() {
+            $crate::unit::with_unit(instance(), |u| u.gate_off());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_all_note_off() {
+            $crate::unit::with_unit(instance(), |u| u.all_note_off());
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_pitch_bend(bend: u16) {
+            $crate::unit::with_unit(instance(), |u| u.pitch_bend(bend));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_channel_pressure(pressure: u8) {
+            $crate::unit::with_unit(instance(), |u| u.channel_pressure(pressure));
+        }
+
+        #[no_mangle]
+        pub extern "C" fn unit_aftertouch(note: u8, aftertouch: u8) {
+            $crate::unit::with_unit(instance(), |u| u.aftertouch(note, aftertouch));
+        }
+    };
+}
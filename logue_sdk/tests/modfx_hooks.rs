@@ -0,0 +1,80 @@
+//! Drives an effect through the hooks `user_modfx_hooks!` generates, the way
+//! the firmware would.
+
+use core::ptr;
+
+use logue_sdk::modfx::{ModFxParam, UserModFx, HOOKS_MAGIC};
+use logue_sdk::oscapi::Platform;
+
+/// Keeps the trait's pass through `process`, and remembers the depth.
+struct Fx {
+    depth: i32,
+}
+
+impl UserModFx for Fx {
+    const PLATFORM: Platform = Platform::Prologue;
+
+    fn init(_platform: u32, _api: u32) -> Self {
+        Fx { depth: -1 }
+    }
+
+    fn param(&mut self, param: ModFxParam, value: i32) {
+        if let ModFxParam::ParamDepth = param {
+            self.depth = value;
+        }
+    }
+}
+
+fn depth() -> i32 {
+    unsafe { (*ptr::addr_of!(INSTANCE)).assume_init_ref().depth }
+}
+
+logue_sdk::user_modfx_hooks!(Fx);
+
+#[test]
+fn hooks_drive_the_effect() {
+    let hooks = &hook_table;
+    assert_eq!(hooks.magic, HOOKS_MAGIC);
+    assert_eq!(hooks.platform, Platform::Prologue as u8);
+    (hooks.func_entry)(Platform::Prologue as u32, 0);
+    (hooks.func_param)(ModFxParam::ParamDepth as u8, 0);
+
+    let main_xn = [0.25f32; 8];
+    let mut main_yn = [0.0f32; 8];
+    let sub_xn = [0.5f32; 8];
+    let mut sub_yn = [0.0f32; 8];
+    (hooks.func_process)(
+        main_xn.as_ptr(),
+        main_yn.as_mut_ptr(),
+        sub_xn.as_ptr(),
+        sub_yn.as_mut_ptr(),
+        4,
+    );
+    assert_eq!(main_yn, main_xn);
+    assert_eq!(sub_yn, sub_xn);
+
+    // Only one side of the sub bus, as on platforms without a sub timbre
+    let mut sub_yn = [1.0f32; 8];
+    (hooks.func_process)(
+        main_xn.as_ptr(),
+        main_yn.as_mut_ptr(),
+        ptr::null(),
+        sub_yn.as_mut_ptr(),
+        4,
+    );
+    assert_eq!(sub_yn, [1.0; 8]);
+    (hooks.func_process)(
+        main_xn.as_ptr(),
+        main_yn.as_mut_ptr(),
+        sub_xn.as_ptr(),
+        ptr::null_mut(),
+        4,
+    );
+
+    assert_eq!(depth(), 0);
+    (hooks.func_param)(ModFxParam::ParamDepth as u8, 1 << 30);
+    assert_eq!(depth(), 1 << 30);
+    // Unknown parameters are ignored
+    (hooks.func_param)(7, 0);
+    assert_eq!(depth(), 1 << 30);
+}
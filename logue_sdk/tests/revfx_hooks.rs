@@ -0,0 +1,51 @@
+//! Drives an effect through the hooks `user_revfx_hooks!` generates, the way
+//! the firmware would.
+
+use logue_sdk::oscapi::Platform;
+use logue_sdk::revfx::{RevFxParam, UserRevFx, HOOKS_MAGIC};
+use logue_sdk::sdram::SdramAllocator;
+
+/// Scales its input by the depth, a q31 fraction.
+struct Fx {
+    depth: f32,
+}
+
+impl UserRevFx for Fx {
+    const PLATFORM: Platform = Platform::NutektDigital;
+
+    fn init(_platform: u32, _api: u32, _sdram: &mut SdramAllocator) -> Self {
+        Fx { depth: 1.0 }
+    }
+
+    fn process(&mut self, xn: &mut [f32]) {
+        for x in xn {
+            *x *= self.depth;
+        }
+    }
+
+    fn param(&mut self, param: RevFxParam, value: i32) {
+        if let RevFxParam::ParamDepth = param {
+            self.depth = value as f32 / (1u32 << 31) as f32;
+        }
+    }
+}
+
+logue_sdk::user_revfx_hooks!(Fx);
+
+#[test]
+fn hooks_drive_the_effect() {
+    let hooks = &hook_table;
+    assert_eq!(hooks.magic, HOOKS_MAGIC);
+    assert_eq!(hooks.platform, Platform::NutektDigital as u8);
+    (hooks.func_entry)(Platform::NutektDigital as u32, 0);
+
+    let mut xn = [1.0f32; 8];
+    (hooks.func_process)(xn.as_mut_ptr(), 2);
+    assert_eq!(xn, [1.0; 8]);
+
+    (hooks.func_param)(RevFxParam::ParamDepth as u8, 1 << 30);
+    // Unknown parameters are ignored
+    (hooks.func_param)(3, 0);
+    (hooks.func_process)(xn.as_mut_ptr(), 2);
+    assert_eq!(xn, [0.5, 0.5, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0]);
+}
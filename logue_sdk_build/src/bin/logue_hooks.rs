@@ -0,0 +1,79 @@
+//! Decode the hook table of a built unit, either the `*_logue` ELF or a
+//! `payload.bin`, and check that it will work on the synth.
+
+use std::env;
+use std::fs;
+use std::process;
+
+use logue_sdk::oscapi::Platform;
+use logue_sdk_build::hooks::{self, HookTable};
+use logue_sdk_build::payload::Payload;
+use logue_sdk_build::version_string;
+
+fn usage(name: &str) -> ! {
+    eprintln!("usage: {name} <unit.elf | payload.bin> [--platform <platform>]");
+    process::exit(2);
+}
+
+fn fail(path: &str, e: impl ToString) -> ! {
+    eprintln!("{path}: {}", e.to_string());
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "logue_hooks".into());
+
+    let mut path = None;
+    let mut platform = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                let p = args.next().unwrap_or_else(|| usage(&name));
+                platform = Some(Platform::from_name(&p).unwrap_or_else(|| usage(&name)));
+            }
+            _ if arg.starts_with('-') || path.is_some() => usage(&name),
+            _ => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        usage(&name);
+    };
+
+    let data = fs::read(&path).unwrap_or_else(|e| fail(&path, e));
+
+    let (table, text, symbols) = if data.starts_with(b"\x7fELF") {
+        let payload = Payload::from_elf(&data).unwrap_or_else(|e| fail(&path, e));
+        let table = HookTable::parse(&payload.data).unwrap_or_else(|e| fail(&path, e));
+        let text = hooks::elf_text(&payload).unwrap_or_else(|| fail(&path, "no .text section"));
+        (table, text, payload.symbols)
+    } else {
+        let table = HookTable::parse(&data).unwrap_or_else(|e| fail(&path, e));
+        let text = hooks::bin_text(&data, &table);
+        (table, text, Vec::new())
+    };
+
+    let header = &table.header;
+    println!(
+        "{} hooks, api {}, platform {}",
+        header.module.name(),
+        version_string(header.api),
+        header.platform.name()
+    );
+    for hook in &table.hooks {
+        let symbol = symbols
+            .iter()
+            .find(|s| s.address == hook.address)
+            .map_or("", |s| s.name.as_str());
+        let line = format!("{:<8} {:#010x} {symbol}", hook.name, hook.address);
+        println!("{}", line.trim_end());
+    }
+
+    let problems = hooks::check(&table, text, platform);
+    for problem in &problems {
+        eprintln!("{path}: {problem}");
+    }
+    if !problems.is_empty() {
+        process::exit(1);
+    }
+}
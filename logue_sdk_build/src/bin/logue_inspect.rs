@@ -0,0 +1,85 @@
+//! Show what is in a unit archive, check that it will load, and optionally
+//! extract its manifest and payload.
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use logue_sdk_build::inspect::{Archive, Inspection};
+use logue_sdk_build::version_string;
+
+fn usage(name: &str) -> ! {
+    eprintln!("usage: {name} <unit archive> [-x <output dir>]");
+    process::exit(2);
+}
+
+fn fail(path: &str, e: impl ToString) -> ! {
+    eprintln!("{path}: {}", e.to_string());
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "logue_inspect".into());
+
+    let mut path = None;
+    let mut extract = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-x" => extract = Some(args.next().unwrap_or_else(|| usage(&name))),
+            _ if arg.starts_with('-') || path.is_some() => usage(&name),
+            _ => path = Some(arg),
+        }
+    }
+    let Some(path) = path else {
+        usage(&name);
+    };
+
+    let archive = Archive::open(Path::new(&path)).unwrap_or_else(|e| fail(&path, e));
+    // Extract before checking, so a broken unit can still be taken apart
+    if let Some(dir) = &extract {
+        archive
+            .extract(Path::new(dir))
+            .unwrap_or_else(|e| fail(dir, e));
+    }
+
+    let unit = Inspection::new(archive).unwrap_or_else(|e| fail(&path, e));
+    let manifest = &unit.manifest;
+    println!(
+        "{} {} {} for {}, api {}",
+        manifest.name,
+        version_string(manifest.version),
+        manifest.module.name(),
+        manifest.platform.name(),
+        version_string(manifest.api)
+    );
+    println!(
+        "dev_id {:#x}, prg_id {:#x}",
+        manifest.dev_id, manifest.prg_id
+    );
+    println!("payload {} bytes", unit.archive.payload.len());
+
+    for hook in &unit.hooks.hooks {
+        println!("{:<8} {:#010x}", hook.name, hook.address);
+    }
+
+    for (i, param) in manifest.params.iter().enumerate() {
+        println!(
+            "param{} {:<12} {}..{} {}",
+            i + 1,
+            param.name,
+            param.min,
+            param.max,
+            param.unit.name()
+        );
+    }
+
+    let extension = Path::new(&path).extension().and_then(|e| e.to_str());
+    let problems = unit.check(extension);
+    for problem in &problems {
+        eprintln!("{path}: {problem}");
+    }
+    if !problems.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,101 @@
+//! Package a linked v1 unit into an archive the librarian can load.
+//!
+//! The manifest defaults to the one `write_manifest` left next to the ELF, so
+//! `target/thumbv7em-none-eabihf/release/noise_logue` is packaged with
+//! `target/thumbv7em-none-eabihf/release/noise.manifest.json`.
+//!
+//! An oscillator's `cycle` hook is run in the emulator first, and packaging
+//! fails if it takes more than `-l` percent of the time its block lasts on the
+//! device, 75% by default. `-l none` skips the measurement.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use logue_sdk_build::emu;
+use logue_sdk_build::pack::{self, Options};
+
+fn usage(name: &str) -> ! {
+    eprintln!(
+        "usage: {name} <unit.elf> [-m <manifest.json>] [-o <output dir>] \
+         [-f <frames>] [-l <max load %> | none]"
+    );
+    process::exit(2);
+}
+
+fn default_manifest(elf: &Path) -> PathBuf {
+    let stem = elf.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let package = stem.strip_suffix("_logue").unwrap_or(stem);
+    elf.with_file_name(format!("{package}.manifest.json"))
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", path.display());
+        process::exit(1);
+    })
+}
+
+fn main() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "logue_pack".into());
+
+    let mut elf = None;
+    let mut manifest = None;
+    let mut out_dir = PathBuf::from(".");
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-m" => manifest = Some(PathBuf::from(args.next().unwrap_or_else(|| usage(&name)))),
+            "-o" => out_dir = PathBuf::from(args.next().unwrap_or_else(|| usage(&name))),
+            "-f" => {
+                options.frames = match args.next().map(|f| f.parse()) {
+                    Some(Ok(frames)) if (1..=emu::MAX_FRAMES).contains(&frames) => frames,
+                    _ => usage(&name),
+                }
+            }
+            "-l" => {
+                options.max_load = match args.next().as_deref() {
+                    Some("none") => None,
+                    Some(percent) => Some(percent.parse().unwrap_or_else(|_| usage(&name))),
+                    None => usage(&name),
+                }
+            }
+            _ if arg.starts_with('-') || elf.is_some() => usage(&name),
+            _ => elf = Some(PathBuf::from(arg)),
+        }
+    }
+    let Some(elf) = elf else {
+        usage(&name);
+    };
+    let manifest = manifest.unwrap_or_else(|| default_manifest(&elf));
+
+    let manifest_json = String::from_utf8(read(&manifest)).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", manifest.display());
+        process::exit(1);
+    });
+
+    match pack::pack(&read(&elf), &manifest_json, &out_dir, &options) {
+        Ok(packed) => {
+            println!(
+                "{}: {} {}, {} byte payload",
+                packed.path.display(),
+                packed.payload.hooks.platform.name(),
+                packed.payload.hooks.module.name(),
+                packed.payload.data.len()
+            );
+            if let Some(load) = packed.load {
+                println!("{load}");
+            }
+        }
+        Err(e @ pack::Error::Write(..)) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}: {e}", elf.display());
+            process::exit(1);
+        }
+    }
+}
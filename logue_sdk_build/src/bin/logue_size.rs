@@ -0,0 +1,44 @@
+//! Report how much of its memory region a linked v1 unit uses, exiting with
+//! an error if it doesn't fit.
+//!
+//! A unit that is too large to link can be broken down from the linker map
+//! the build leaves next to it, e.g. `target/thumbv7em-none-eabihf/release/noise.map`.
+
+use std::env;
+use std::fs;
+use std::process;
+
+use logue_sdk_build::budget::{map_symbols, Usage, TOP_SYMBOLS};
+use logue_sdk_build::payload::Payload;
+
+fn fail(path: &str, e: impl ToString) -> ! {
+    eprintln!("{path}: {}", e.to_string());
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "logue_size".into());
+    let (Some(path), None) = (args.next(), args.next()) else {
+        eprintln!("usage: {name} <unit.elf | unit.map>");
+        process::exit(2);
+    };
+
+    let data = fs::read(&path).unwrap_or_else(|e| fail(&path, e));
+
+    if path.ends_with(".map") {
+        let map = String::from_utf8_lossy(&data);
+        println!("largest input sections:");
+        for sym in map_symbols(&map).iter().take(TOP_SYMBOLS) {
+            println!("{:>8} {:<16} {}", sym.size, sym.section, sym.name);
+        }
+        return;
+    }
+
+    let payload = Payload::from_elf(&data).unwrap_or_else(|e| fail(&path, e));
+    let usage = Usage::new(&payload);
+    print!("{usage}");
+    if usage.overflows() {
+        fail(&path, "unit does not fit in memory");
+    }
+}
@@ -0,0 +1,250 @@
+//! How much of the unit's memory a payload uses.
+//!
+//! Each v1 module is loaded into a fixed SRAM region, which has to hold the
+//! payload and `.bss`. The linker refuses to link a unit that overflows the
+//! region in its linker script, but the limits are checked again here so that
+//! packaging reports what is using the space.
+
+use std::fmt;
+use std::ops::Range;
+
+use logue_sdk::manifest::Module;
+
+use crate::payload::{Payload, Symbol};
+
+/// Symbols listed when reporting usage.
+pub const TOP_SYMBOLS: usize = 10;
+
+/// Size of the SRAM region a module is loaded into, matching the linker
+/// scripts in `ld/`.
+pub fn region_size(module: Module) -> u32 {
+    match module {
+        Module::Osc => 32 * 1024,
+        Module::ModFx => 16 * 1024,
+        Module::DelFx | Module::RevFx => 24 * 1024,
+    }
+}
+
+/// Where delay and reverb effects' `.sdram` buffers go, matching `SDRAM` in
+/// their linker scripts. It's outside the module's region and not loaded.
+pub const SDRAM: Range<u32> = 0xC000_0000..0xC000_0000 + 3 * 1024 * 1024;
+
+pub struct Usage<'a> {
+    payload: &'a Payload,
+    pub limit: u32,
+    /// Bytes loaded from the payload.
+    pub loaded: u32,
+    /// Bytes of the region used once loaded, including `.bss`.
+    pub memory: u32,
+}
+
+impl<'a> Usage<'a> {
+    pub fn new(payload: &'a Payload) -> Self {
+        let limit = region_size(payload.hooks.module);
+        // Everything in SRAM, including sections past the end of the region
+        let end = payload
+            .sections
+            .iter()
+            .filter(|s| s.address >= payload.base && !SDRAM.contains(&s.address))
+            .map(|s| s.end())
+            .max()
+            .unwrap_or(payload.base);
+
+        Self {
+            payload,
+            limit,
+            loaded: payload.data.len() as u32,
+            memory: end - payload.base,
+        }
+    }
+
+    pub fn overflows(&self) -> bool {
+        self.memory > self.limit
+    }
+}
+
+fn percent(used: u32, limit: u32) -> f64 {
+    used as f64 * 100.0 / limit as f64
+}
+
+impl fmt::Display for Usage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16} {:>10} {:>8}", "section", "address", "size")?;
+        for s in &self.payload.sections {
+            let note = if s.loaded { "" } else { " (not loaded)" };
+            writeln!(f, "{:<16} {:#010x} {:>8}{note}", s.name, s.address, s.size)?;
+        }
+
+        writeln!(
+            f,
+            "payload {} bytes, memory {} of {} bytes ({:.1}%)",
+            self.loaded,
+            self.memory,
+            self.limit,
+            percent(self.memory, self.limit)
+        )?;
+
+        writeln!(f, "largest symbols:")?;
+        for sym in self.payload.symbols.iter().take(TOP_SYMBOLS) {
+            writeln!(f, "{:>8} {:<16} {}", sym.size, sym.section, sym.name)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The input sections placed by the linker, from the map file it writes even
+/// when the unit doesn't fit. Functions and statics each get their own
+/// section, so these stand in for the symbols of a unit that didn't link.
+/// Largest first.
+pub fn map_symbols(map: &str) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut output_section = "";
+
+    for line in map.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [vma, _lma, size, _align, name, ..] = fields[..] else {
+            continue;
+        };
+        let (Ok(address), Ok(size)) = (u32::from_str_radix(vma, 16), u32::from_str_radix(size, 16))
+        else {
+            continue;
+        };
+
+        // Output sections are named directly, input sections as
+        // `<object>:(<section>)`.
+        let Some((_, input)) = name.rsplit_once(":(") else {
+            if fields.len() == 5 && name.starts_with('.') {
+                output_section = name;
+            }
+            continue;
+        };
+        // Sections that aren't loaded, like the symbol table, are at zero
+        if size == 0 || address == 0 {
+            continue;
+        }
+
+        let input = input.trim_end_matches(')');
+        let mangled = [".text.", ".rodata.", ".data.", ".bss."]
+            .iter()
+            .find_map(|prefix| input.strip_prefix(prefix))
+            .unwrap_or(input);
+
+        symbols.push(Symbol {
+            name: format!("{:#}", rustc_demangle::demangle(mangled)),
+            section: output_section.to_owned(),
+            address,
+            size,
+        });
+    }
+
+    symbols.sort_by(|a, b| b.size.cmp(&a.size).then(a.address.cmp(&b.address)));
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::{HooksHeader, Section};
+    use logue_sdk::oscapi::{Platform, API_VERSION};
+
+    const MAP: &str = "
+     VMA      LMA     Size Align Out     In      Symbol
+20000000 20000000       2c    16 .hooks
+20000000 20000000       2c     4         unit.o:(.hooks)
+20000000 20000000        0     1                 hook_table
+2000002c 2000002c       42     4 .text
+2000002c 2000002c       42     4         unit.o:(.text._ZN9logue_sdk7runtime4init17h0596802d55869e04E)
+2000006e 2000006e        2     1         . = ALIGN(4)
+20000070 20000070     9c40     4 .rodata
+20000070 20000070     9c40     4         unit.o:(.rodata.BIG)
+       0        0      6da     1 .strtab
+       0        0      6da     1         <internal>:(.strtab)
+";
+
+    #[test]
+    fn map_symbols_largest_first() {
+        let symbols: Vec<_> = map_symbols(MAP)
+            .into_iter()
+            .map(|s| (s.size, s.section, s.name))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                (0x9c40, ".rodata".into(), "BIG".into()),
+                (0x42, ".text".into(), "logue_sdk::runtime::init".into()),
+                (0x2c, ".hooks".into(), ".hooks".into()),
+            ]
+        );
+    }
+
+    fn section(name: &str, address: u32, size: u32, loaded: bool) -> Section {
+        Section {
+            name: name.into(),
+            address,
+            size,
+            loaded,
+        }
+    }
+
+    fn payload(module: Module, sections: Vec<Section>) -> Payload {
+        Payload {
+            base: 0x2000_0000,
+            data: vec![0; 0x7000],
+            sections,
+            symbols: Vec::new(),
+            hooks: HooksHeader {
+                module,
+                api: API_VERSION,
+                platform: Platform::Prologue,
+            },
+        }
+    }
+
+    #[test]
+    fn bss_counts_towards_memory() {
+        let payload = payload(
+            Module::Osc,
+            vec![
+                section(".text", 0x2000_0000, 0x7000, true),
+                section(".bss", 0x2000_7000, 0x1001, false),
+            ],
+        );
+
+        let usage = Usage::new(&payload);
+        assert_eq!((usage.loaded, usage.memory), (0x7000, 0x8001));
+        assert!(usage.overflows());
+    }
+
+    #[test]
+    fn sdram_does_not_count_towards_memory() {
+        let payload = payload(
+            Module::DelFx,
+            vec![
+                section(".text", 0x2000_0000, 0x1000, true),
+                section(".bss", 0x2000_1000, 0x100, false),
+                section(".sdram", 0xC000_0000, 0x10_0000, false),
+            ],
+        );
+
+        let usage = Usage::new(&payload);
+        assert_eq!(usage.memory, 0x1100);
+        assert!(!usage.overflows());
+    }
+
+    #[test]
+    fn bss_past_the_region_overflows() {
+        let payload = payload(
+            Module::DelFx,
+            vec![
+                section(".text", 0x2000_0000, 0x6000, true),
+                section(".bss", 0x2000_6000, 0x10, false),
+                section(".sdram", 0xC000_0000, 0x10_0000, false),
+            ],
+        );
+
+        let usage = Usage::new(&payload);
+        assert_eq!(usage.memory, 0x6010);
+        assert!(usage.overflows());
+    }
+}
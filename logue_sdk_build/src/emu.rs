@@ -0,0 +1,429 @@
+//! Running a built oscillator on an emulated Cortex-M4, so that what ships can
+//! be checked against what is tested on the host.
+//!
+//! The payload is loaded at the address `userosc.ld` links it for, and the
+//! firmware's tables are mapped at their addresses in `osc_api.syms`, filled
+//! from the SDK's `internal_luts`. When the unit branches to a firmware
+//! function it runs natively instead.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::offset_of;
+
+use logue_sdk::manifest::Module;
+use logue_sdk::oscapi::{self, Platform, UserOscParam, API_VERSION};
+
+use crate::budget::region_size;
+use crate::hooks::{HookTable, LOAD_ADDRESS};
+use crate::payload::{self, Payload};
+
+mod cpu;
+mod memory;
+mod timing;
+mod vfp;
+
+use cpu::{Cpu, Trap, LR, PC, SP};
+use memory::Memory;
+
+pub use memory::Fault;
+
+const OSC_API_SYMS: &str = include_str!("../ld/osc_api.syms");
+
+/// The firmware's tables and functions, around the addresses in
+/// `osc_api.syms`.
+const FLASH: u32 = 0x0800_0000;
+const FLASH_SIZE: usize = 0x2_0000;
+
+/// The stack each hook is called with, outside the unit's region.
+const STACK: u32 = 0x2001_0000;
+const STACK_SIZE: usize = 0x4000;
+
+/// Where the parameters and buffer passed to hooks live.
+const HOST: u32 = 0x3000_0000;
+const HOST_SIZE: usize = 0x1000;
+const PARAMS: u32 = HOST;
+const BUFFER: u32 = HOST + 0x100;
+/// The most frames `cycle` can be asked for at once.
+pub const MAX_FRAMES: usize = (HOST_SIZE - 0x100) / 4;
+
+/// What hooks return to, which nothing is mapped at.
+const RETURN_ADDRESS: u32 = 0xFFFF_FFFE;
+
+/// A guess at the cycles taken by each of the firmware's functions, which are
+/// all short.
+const FIRMWARE_CYCLES: u64 = 16;
+
+/// Instructions a single hook can run before it's assumed to be stuck.
+const MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+#[derive(Debug)]
+pub enum Error {
+    Payload(payload::Error),
+    NotOsc(Module),
+    TooLarge {
+        size: usize,
+        limit: u32,
+    },
+    Memory {
+        pc: u32,
+        fault: Fault,
+    },
+    Undefined {
+        pc: u32,
+        instruction: u32,
+    },
+    /// The unit called a firmware function that isn't emulated.
+    Firmware(&'static str),
+    /// The hook ran for `MAX_INSTRUCTIONS` without returning.
+    Timeout(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Payload(e) => e.fmt(f),
+            Error::NotOsc(module) => write!(f, "{} units can't be emulated", module.name()),
+            Error::TooLarge { size, limit } => {
+                write!(
+                    f,
+                    "payload is {size} bytes, more than the {limit} available"
+                )
+            }
+            Error::Memory { pc, fault } => write!(
+                f,
+                "{} {:#010x} at pc {pc:#010x}",
+                if fault.write {
+                    "invalid write to"
+                } else {
+                    "invalid read from"
+                },
+                fault.address
+            ),
+            Error::Undefined { pc, instruction } => {
+                write!(f, "undefined instruction {instruction:#x} at pc {pc:#010x}")
+            }
+            Error::Firmware(name) => write!(f, "firmware function {name} isn't emulated"),
+            Error::Timeout(hook) => write!(f, "{hook} hook didn't return"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<payload::Error> for Error {
+    fn from(e: payload::Error) -> Self {
+        Error::Payload(e)
+    }
+}
+
+/// The firmware symbols in `osc_api.syms`, in address order.
+fn firmware_symbols() -> impl Iterator<Item = (&'static str, u32)> {
+    OSC_API_SYMS.lines().filter_map(|line| {
+        let (name, address) = line.trim().trim_end_matches(';').split_once('=')?;
+        let address = address.trim().strip_prefix("0x")?;
+        Some((name.trim(), u32::from_str_radix(address, 16).ok()?))
+    })
+}
+
+fn lut(name: &str) -> Option<&'static [f32]> {
+    unsafe {
+        Some(match name {
+            "midi_to_hz_lut_f" => &oscapi::midi_to_hz_lut_f,
+            "sqrtm2log_lut_f" => &oscapi::sqrtm2log_lut_f,
+            "tanpi_lut_f" => &oscapi::tanpi_lut_f,
+            "log_lut_f" => &oscapi::log_lut_f,
+            "bitres_lut_f" => &oscapi::bitres_lut_f,
+            "wt_par_lut_f" => &oscapi::wt_par_lut_f,
+            "wt_sqr_lut_f" => &oscapi::wt_sqr_lut_f,
+            "wt_saw_lut_f" => &oscapi::wt_saw_lut_f,
+            "wt_sine_lut_f" => &oscapi::wt_sine_lut_f,
+            "schetzen_lut_f" => &oscapi::schetzen_lut_f,
+            "cubicsat_lut_f" => &oscapi::cubicsat_lut_f,
+            _ => return None,
+        })
+    }
+}
+
+fn notes(name: &str) -> Option<&'static [u8]> {
+    unsafe {
+        Some(match name {
+            "wt_par_notes" => &oscapi::wt_par_notes,
+            "wt_sqr_notes" => &oscapi::wt_sqr_notes,
+            "wt_saw_notes" => &oscapi::wt_saw_notes,
+            _ => return None,
+        })
+    }
+}
+
+fn waves(name: &str) -> Option<&'static [&'static [f32; 129]]> {
+    unsafe {
+        Some(match name {
+            "wavesA" => &oscapi::wavesA,
+            "wavesB" => &oscapi::wavesB,
+            "wavesC" => &oscapi::wavesC,
+            "wavesD" => &oscapi::wavesD,
+            "wavesE" => &oscapi::wavesE,
+            "wavesF" => &oscapi::wavesF,
+            _ => return None,
+        })
+    }
+}
+
+fn f32_bytes(data: &[f32]) -> Vec<u8> {
+    data.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub struct Emulator {
+    cpu: Cpu,
+    mem: Memory,
+    hooks: HookTable,
+    /// Firmware functions by address.
+    firmware: HashMap<u32, &'static str>,
+    rand: u32,
+    /// Instructions run by the last hook called.
+    pub instructions: u64,
+    /// Estimated device cycles taken by the last hook called.
+    pub cycles: u64,
+}
+
+impl Emulator {
+    /// Load a `payload.bin`, for the platform its hook table names.
+    pub fn new(payload: &[u8]) -> Result<Self, Error> {
+        let hooks = HookTable::parse(payload)?;
+        if hooks.header.module != Module::Osc {
+            return Err(Error::NotOsc(hooks.header.module));
+        }
+        let limit = region_size(Module::Osc);
+        if payload.len() > limit as usize {
+            return Err(Error::TooLarge {
+                size: payload.len(),
+                limit,
+            });
+        }
+
+        let mut mem = Memory::default();
+        mem.map(LOAD_ADDRESS, limit as usize, true);
+        mem.map(STACK, STACK_SIZE, true);
+        mem.map(HOST, HOST_SIZE, true);
+        mem.map(FLASH, FLASH_SIZE, false);
+        mem.load(LOAD_ADDRESS, payload).unwrap();
+
+        let mut firmware = HashMap::new();
+        for (name, address) in firmware_symbols() {
+            let data = match name {
+                _ if name.starts_with("_osc_") => {
+                    firmware.insert(address, name);
+                    continue;
+                }
+                "k_osc_api_version" => API_VERSION.to_le_bytes().to_vec(),
+                "k_osc_api_platform" => u32::from(hooks.header.platform as u8)
+                    .to_le_bytes()
+                    .to_vec(),
+                _ if lut(name).is_some() => f32_bytes(lut(name).unwrap()),
+                _ if notes(name).is_some() => notes(name).unwrap().to_vec(),
+                _ => {
+                    let tables = waves(name).expect("osc_api.syms names unknown firmware data");
+                    // The waves follow the table of pointers to them
+                    let mut pointers = Vec::new();
+                    let mut data = Vec::new();
+                    let start = address + 4 * tables.len() as u32;
+                    for wave in tables {
+                        pointers.extend((start + data.len() as u32).to_le_bytes());
+                        data.extend(f32_bytes(*wave));
+                    }
+                    pointers.extend(data);
+                    pointers
+                }
+            };
+            mem.load(address, &data).unwrap();
+        }
+
+        Ok(Self {
+            cpu: Cpu::default(),
+            mem,
+            hooks,
+            firmware,
+            rand: 0x2545_f491,
+            instructions: 0,
+            cycles: 0,
+        })
+    }
+
+    /// Load the payload of a linked `*_logue` unit.
+    pub fn from_elf(elf: &[u8]) -> Result<Self, Error> {
+        Self::new(&Payload::from_elf(elf)?.data)
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.hooks.header.platform
+    }
+
+    fn run_firmware(&mut self, name: &'static str) -> Result<(), Error> {
+        let arg = f32::from_bits(self.cpu.s[0]);
+        match name {
+            "_osc_white" => self.cpu.s[0] = oscapi::osc_white().to_bits(),
+            "_osc_bl_saw_idx" => self.cpu.s[0] = oscapi::osc_bl_saw_idx(arg).to_bits(),
+            "_osc_bl_sqr_idx" => self.cpu.s[0] = oscapi::osc_bl_sqr_idx(arg).to_bits(),
+            "_osc_bl_par_idx" => self.cpu.s[0] = oscapi::osc_bl_par_idx(arg).to_bits(),
+            "_osc_rand" => {
+                self.rand ^= self.rand << 13;
+                self.rand ^= self.rand >> 17;
+                self.rand ^= self.rand << 5;
+                self.cpu.r[0] = self.rand;
+            }
+            "_osc_mcu_hash" => self.cpu.r[0] = 0,
+            _ => return Err(Error::Firmware(name)),
+        }
+        self.cpu.r[PC] = self.cpu.r[LR] & !1;
+        Ok(())
+    }
+
+    /// Call one of `UserOscHookTable::HOOK_NAMES` with integer arguments, and
+    /// run it until it returns.
+    pub fn call(&mut self, hook: &str, args: &[u32]) -> Result<(), Error> {
+        let hook = self
+            .hooks
+            .hooks
+            .iter()
+            .find(|h| h.name == hook)
+            .expect("not an oscillator hook")
+            .clone();
+
+        self.cpu.r[..args.len()].copy_from_slice(args);
+        self.cpu.r[SP] = STACK + STACK_SIZE as u32;
+        self.cpu.r[LR] = RETURN_ADDRESS | 1;
+        self.cpu.r[PC] = hook.address & !1;
+
+        let start = self.cpu.instructions;
+        let start_cycles = self.cpu.cycles;
+        while self.cpu.r[PC] != RETURN_ADDRESS {
+            if self.cpu.instructions - start > MAX_INSTRUCTIONS {
+                return Err(Error::Timeout(hook.name));
+            }
+
+            let pc = self.cpu.r[PC];
+            if let Some(&name) = self.firmware.get(&pc) {
+                self.run_firmware(name)?;
+                self.cpu.cycles += FIRMWARE_CYCLES;
+                continue;
+            }
+            self.cpu.step(&mut self.mem).map_err(|trap| match trap {
+                Trap::Memory(fault) => Error::Memory { pc, fault },
+                Trap::Undefined(instruction) => Error::Undefined { pc, instruction },
+            })?;
+        }
+
+        self.instructions = self.cpu.instructions - start;
+        self.cycles = self.cpu.cycles - start_cycles;
+        Ok(())
+    }
+
+    fn write_params(&mut self, params: &UserOscParam) {
+        let fields = [
+            (offset_of!(UserOscParam, pitch), params.pitch),
+            (offset_of!(UserOscParam, cutoff), params.cutoff),
+            (offset_of!(UserOscParam, resonance), params.resonance),
+        ];
+        let shape_lfo = PARAMS + offset_of!(UserOscParam, shape_lfo) as u32;
+        self.mem
+            .write32(shape_lfo, params.shape_lfo as u32)
+            .unwrap();
+        for (offset, value) in fields {
+            self.mem.write16(PARAMS + offset as u32, value).unwrap();
+        }
+    }
+
+    /// Start the unit, as the firmware does once after loading it.
+    pub fn init(&mut self) -> Result<(), Error> {
+        self.call("entry", &[u32::from(self.platform() as u8), API_VERSION])
+    }
+
+    pub fn cycle(&mut self, params: &UserOscParam, buf: &mut [i32]) -> Result<(), Error> {
+        assert!(buf.len() <= MAX_FRAMES, "too many frames to emulate");
+        self.write_params(params);
+        self.call("cycle", &[PARAMS, BUFFER, buf.len() as u32])?;
+        for (i, x) in buf.iter_mut().enumerate() {
+            *x = self.mem.read32(BUFFER + 4 * i as u32).unwrap() as i32;
+        }
+        Ok(())
+    }
+
+    pub fn note_on(&mut self, params: &UserOscParam) -> Result<(), Error> {
+        self.write_params(params);
+        self.call("on", &[PARAMS])
+    }
+
+    pub fn note_off(&mut self, params: &UserOscParam) -> Result<(), Error> {
+        self.write_params(params);
+        self.call("off", &[PARAMS])
+    }
+
+    pub fn mute(&mut self, params: &UserOscParam) -> Result<(), Error> {
+        self.write_params(params);
+        self.call("mute", &[PARAMS])
+    }
+
+    pub fn value(&mut self, value: u16) -> Result<(), Error> {
+        self.call("value", &[u32::from(value)])
+    }
+
+    pub fn param(&mut self, idx: u16, value: u16) -> Result<(), Error> {
+        self.call("param", &[u32::from(idx), u32::from(value)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logue_sdk::oscapi::HOOKS_MAGIC;
+
+    /// A payload whose hooks all point at `code`, placed after the table.
+    fn payload(code: &[u16]) -> Vec<u8> {
+        let entry = LOAD_ADDRESS + 44;
+        let mut data = Vec::new();
+        data.extend(HOOKS_MAGIC);
+        data.extend(API_VERSION.to_le_bytes());
+        data.push(Platform::Prologue as u8);
+        data.extend([0; 7]);
+        for _ in 0..7 {
+            data.extend((entry | 1).to_le_bytes());
+        }
+        data.extend(code.iter().flat_map(|h| h.to_le_bytes()));
+        data
+    }
+
+    #[test]
+    fn reads_firmware_tables() {
+        // movw r3, #:lower16:midi_to_hz_lut_f; movt r3, #:upper16:...;
+        // vldr s0, [r3, #276]; vstr s0, [r1]; bx lr
+        let mut emu = Emulator::new(&payload(&[
+            0xf24f, 0x1300, 0xf6c0, 0x0300, 0xed93, 0x0a45, 0xed81, 0x0a00, 0x4770,
+        ]))
+        .unwrap();
+
+        let mut buf = [0; 1];
+        emu.cycle(&UserOscParam::default(), &mut buf).unwrap();
+        assert_eq!(f32::from_bits(buf[0] as u32), 440.0);
+        assert_eq!(emu.instructions, 5);
+        // The FPU load and store take two each, and the return refills the
+        // pipeline
+        assert_eq!(emu.cycles, 1 + 1 + 2 + 2 + 1 + 2);
+    }
+
+    #[test]
+    fn reports_faults() {
+        // ldr r0, [r0]; bx lr
+        let mut emu = Emulator::new(&payload(&[0x6800, 0x4770])).unwrap();
+        let e = emu.value(0x10).unwrap_err();
+        assert!(matches!(
+            e,
+            Error::Memory {
+                pc: 0x2000_002c,
+                fault: Fault {
+                    address: 0x10,
+                    write: false
+                }
+            }
+        ));
+    }
+}
@@ -0,0 +1,1141 @@
+//! A Cortex-M4 core: the Thumb and Thumb-2 instructions rustc emits for
+//! `thumbv7em-none-eabihf`, with the single precision FPU in `vfp`.
+//!
+//! Decoding follows the encoding tables of the ARMv7-M Architecture Reference
+//! Manual. Instructions a unit has no use for, like exclusive monitors beyond
+//! always succeeding, system registers and the DSP extension's parallel
+//! arithmetic, decode as undefined.
+
+use super::memory::{Fault, Memory};
+use super::timing::{self, BRANCH_REFILL};
+
+pub const SP: usize = 13;
+pub const LR: usize = 14;
+pub const PC: usize = 15;
+
+const LSL: u32 = 0;
+const LSR: u32 = 1;
+const ASR: u32 = 2;
+const ROR: u32 = 3;
+const RRX: u32 = 4;
+
+/// Why an instruction couldn't complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trap {
+    Memory(Fault),
+    Undefined(u32),
+}
+
+impl From<Fault> for Trap {
+    fn from(fault: Fault) -> Self {
+        Trap::Memory(fault)
+    }
+}
+
+#[derive(Default)]
+pub struct Cpu {
+    pub r: [u32; 16],
+    /// The FPU's single precision registers, as bits.
+    pub s: [u32; 32],
+    n: bool,
+    z: bool,
+    c: bool,
+    v: bool,
+    pub(super) fpscr: u32,
+    /// ITSTATE: the condition and mask of the IT block being executed.
+    it: u8,
+    /// Where execution continues after the current instruction.
+    next: u32,
+    /// Instructions executed, including those skipped by their condition.
+    pub instructions: u64,
+    /// An estimate of the cycles the instructions would take on the device.
+    pub cycles: u64,
+}
+
+fn add_with_carry(x: u32, y: u32, carry: bool) -> (u32, bool, bool) {
+    let unsigned = u64::from(x) + u64::from(y) + u64::from(carry);
+    let signed = i64::from(x as i32) + i64::from(y as i32) + i64::from(carry);
+    let result = unsigned as u32;
+    (
+        result,
+        unsigned >> 32 != 0,
+        i64::from(result as i32) != signed,
+    )
+}
+
+fn shift_c(value: u32, kind: u32, amount: u32, carry: bool) -> (u32, bool) {
+    if amount == 0 && kind != RRX {
+        return (value, carry);
+    }
+    match kind {
+        LSL if amount < 32 => (value << amount, (value >> (32 - amount)) & 1 != 0),
+        LSL => (0, amount == 32 && value & 1 != 0),
+        LSR if amount < 32 => (value >> amount, (value >> (amount - 1)) & 1 != 0),
+        LSR => (0, amount == 32 && value >> 31 != 0),
+        ASR if amount < 32 => (
+            ((value as i32) >> amount) as u32,
+            (value >> (amount - 1)) & 1 != 0,
+        ),
+        ASR => {
+            let sign = ((value as i32) >> 31) as u32;
+            (sign, sign != 0)
+        }
+        ROR => {
+            let result = value.rotate_right(amount % 32);
+            (result, result >> 31 != 0)
+        }
+        _ => (u32::from(carry) << 31 | value >> 1, value & 1 != 0),
+    }
+}
+
+/// The shift of an instruction with an immediate shift amount, where zero
+/// means 32 for right shifts and RRX for rotates.
+fn decode_imm_shift(kind: u32, imm5: u32) -> (u32, u32) {
+    match (kind, imm5) {
+        (LSL, _) => (LSL, imm5),
+        (LSR | ASR, 0) => (kind, 32),
+        (ROR, 0) => (RRX, 1),
+        _ => (kind, imm5),
+    }
+}
+
+fn thumb_expand_imm_c(imm12: u32, carry: bool) -> (u32, bool) {
+    if imm12 >> 10 == 0 {
+        let imm8 = imm12 & 0xFF;
+        let value = match (imm12 >> 8) & 3 {
+            0 => imm8,
+            1 => imm8 << 16 | imm8,
+            2 => imm8 << 24 | imm8 << 8,
+            _ => imm8 * 0x0101_0101,
+        };
+        (value, carry)
+    } else {
+        let value = (0x80 | imm12 & 0x7F).rotate_right(imm12 >> 7);
+        (value, value >> 31 != 0)
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u32 {
+    let shift = 32 - bits;
+    (((value << shift) as i32) >> shift) as u32
+}
+
+fn signed_sat(value: i64, bits: u32) -> u32 {
+    let max = (1i64 << (bits - 1)) - 1;
+    value.clamp(-max - 1, max) as u32
+}
+
+fn unsigned_sat(value: i64, bits: u32) -> u32 {
+    value.clamp(0, (1i64 << bits) - 1) as u32
+}
+
+fn align4(address: u32) -> u32 {
+    address & !3
+}
+
+impl Cpu {
+    /// The value of a register as an operand, where the PC reads as the
+    /// current instruction plus four.
+    fn reg(&self, n: u32) -> u32 {
+        let n = n as usize;
+        if n == PC {
+            self.r[PC].wrapping_add(4)
+        } else {
+            self.r[n]
+        }
+    }
+
+    /// The PC as an operand.
+    fn pc(&self) -> u32 {
+        self.r[PC].wrapping_add(4)
+    }
+
+    fn set_reg(&mut self, n: u32, value: u32) {
+        if n as usize == PC {
+            self.branch(value);
+        } else {
+            self.r[n as usize] = value;
+        }
+    }
+
+    fn branch(&mut self, address: u32) {
+        self.next = address & !1;
+    }
+
+    fn in_it_block(&self) -> bool {
+        self.it & 0xF != 0
+    }
+
+    fn advance_it(&mut self) {
+        if self.it & 7 == 0 {
+            self.it = 0;
+        } else {
+            self.it = self.it & 0xE0 | (self.it << 1) & 0x1F;
+        }
+    }
+
+    fn condition_passed(&self, cond: u32) -> bool {
+        let result = match cond >> 1 {
+            0 => self.z,
+            1 => self.c,
+            2 => self.n,
+            3 => self.v,
+            4 => self.c && !self.z,
+            5 => self.n == self.v,
+            6 => self.n == self.v && !self.z,
+            _ => return true,
+        };
+        result != (cond & 1 != 0)
+    }
+
+    /// Copy the FPU's comparison flags to the APSR, for `vmrs APSR_nzcv`.
+    pub(super) fn set_nzcv(&mut self, nzcv: u32) {
+        self.n = nzcv & 8 != 0;
+        self.z = nzcv & 4 != 0;
+        self.c = nzcv & 2 != 0;
+        self.v = nzcv & 1 != 0;
+    }
+
+    fn set_nz(&mut self, result: u32) {
+        self.n = result >> 31 != 0;
+        self.z = result == 0;
+    }
+
+    fn logical(&mut self, result: u32, carry: bool, setflags: bool) -> u32 {
+        if setflags {
+            self.set_nz(result);
+            self.c = carry;
+        }
+        result
+    }
+
+    fn arith(&mut self, x: u32, y: u32, carry: bool, setflags: bool) -> u32 {
+        let (result, c, v) = add_with_carry(x, y, carry);
+        if setflags {
+            self.set_nz(result);
+            self.c = c;
+            self.v = v;
+        }
+        result
+    }
+
+    /// Execute the instruction at the PC.
+    pub fn step(&mut self, mem: &mut Memory) -> Result<(), Trap> {
+        let pc = self.r[PC];
+        let hw1 = u32::from(mem.read16(pc)?);
+        let wide = hw1 >> 11 >= 0b11101;
+        let hw2 = if wide {
+            u32::from(mem.read16(pc.wrapping_add(2))?)
+        } else {
+            0
+        };
+        let sequential = pc.wrapping_add(if wide { 4 } else { 2 });
+        self.next = sequential;
+        self.instructions += 1;
+
+        let in_it_block = self.in_it_block();
+        if !in_it_block || self.condition_passed(u32::from(self.it >> 4)) {
+            if wide {
+                self.exec32(mem, hw1, hw2)?;
+            } else {
+                self.exec16(mem, hw1)?;
+            }
+            let refill = if self.next != sequential {
+                BRANCH_REFILL
+            } else {
+                0
+            };
+            self.cycles += u64::from(timing::cycles(hw1, hw2, wide) + refill);
+        } else {
+            self.cycles += 1;
+        }
+        if in_it_block {
+            self.advance_it();
+        }
+
+        self.r[PC] = self.next;
+        Ok(())
+    }
+
+    fn exec16(&mut self, mem: &mut Memory, op: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(op));
+        let setflags = !self.in_it_block();
+        let rd = op & 7;
+        let rn = (op >> 3) & 7;
+
+        match op >> 10 {
+            // Shift, add, subtract, move and compare
+            0b000000..=0b001111 => match (op >> 11) & 7 {
+                kind @ 0..=2 => {
+                    let (kind, amount) = decode_imm_shift(kind, (op >> 6) & 0x1F);
+                    let (result, carry) = shift_c(self.reg(rn), kind, amount, self.c);
+                    let result = self.logical(result, carry, setflags);
+                    self.set_reg(rd, result);
+                }
+                3 => {
+                    let operand = if op & 0x400 != 0 {
+                        (op >> 6) & 7
+                    } else {
+                        self.reg((op >> 6) & 7)
+                    };
+                    let result = if op & 0x200 != 0 {
+                        self.arith(self.reg(rn), !operand, true, setflags)
+                    } else {
+                        self.arith(self.reg(rn), operand, false, setflags)
+                    };
+                    self.set_reg(rd, result);
+                }
+                opc => {
+                    let rdn = (op >> 8) & 7;
+                    let imm8 = op & 0xFF;
+                    match opc {
+                        4 => {
+                            let result = self.logical(imm8, self.c, setflags);
+                            self.set_reg(rdn, result);
+                        }
+                        5 => {
+                            self.arith(self.reg(rdn), !imm8, true, true);
+                        }
+                        6 => {
+                            let result = self.arith(self.reg(rdn), imm8, false, setflags);
+                            self.set_reg(rdn, result);
+                        }
+                        _ => {
+                            let result = self.arith(self.reg(rdn), !imm8, true, setflags);
+                            self.set_reg(rdn, result);
+                        }
+                    }
+                }
+            },
+
+            // Data processing
+            0b010000 => {
+                let (x, y) = (self.reg(rd), self.reg(rn));
+                let result = match (op >> 6) & 0xF {
+                    0b0000 => Some(self.logical(x & y, self.c, setflags)),
+                    0b0001 => Some(self.logical(x ^ y, self.c, setflags)),
+                    kind @ (0b0010 | 0b0011 | 0b0100 | 0b0111) => {
+                        let kind = match kind {
+                            0b0010 => LSL,
+                            0b0011 => LSR,
+                            0b0100 => ASR,
+                            _ => ROR,
+                        };
+                        let (result, carry) = shift_c(x, kind, y & 0xFF, self.c);
+                        Some(self.logical(result, carry, setflags))
+                    }
+                    0b0101 => Some(self.arith(x, y, self.c, setflags)),
+                    0b0110 => Some(self.arith(x, !y, self.c, setflags)),
+                    0b1000 => {
+                        self.logical(x & y, self.c, true);
+                        None
+                    }
+                    0b1001 => Some(self.arith(!y, 0, true, setflags)),
+                    0b1010 => {
+                        self.arith(x, !y, true, true);
+                        None
+                    }
+                    0b1011 => {
+                        self.arith(x, y, false, true);
+                        None
+                    }
+                    0b1100 => Some(self.logical(x | y, self.c, setflags)),
+                    0b1101 => {
+                        let result = x.wrapping_mul(y);
+                        if setflags {
+                            self.set_nz(result);
+                        }
+                        Some(result)
+                    }
+                    0b1110 => Some(self.logical(x & !y, self.c, setflags)),
+                    _ => Some(self.logical(!y, self.c, setflags)),
+                };
+                if let Some(result) = result {
+                    self.set_reg(rd, result);
+                }
+            }
+
+            // Special data processing and branch and exchange
+            0b010001 => {
+                let rdn = (op >> 4) & 8 | op & 7;
+                let rm = (op >> 3) & 0xF;
+                match (op >> 8) & 3 {
+                    0 => {
+                        let result = self.reg(rdn).wrapping_add(self.reg(rm));
+                        self.set_reg(rdn, result);
+                    }
+                    1 => {
+                        self.arith(self.reg(rdn), !self.reg(rm), true, true);
+                    }
+                    2 => self.set_reg(rdn, self.reg(rm)),
+                    _ => {
+                        let target = self.reg(rm);
+                        if op & 0x80 != 0 {
+                            self.r[LR] = self.next | 1;
+                        }
+                        self.branch(target);
+                    }
+                }
+            }
+
+            // LDR (literal)
+            0b010010 | 0b010011 => {
+                let address = align4(self.pc()) + (op & 0xFF) * 4;
+                self.r[((op >> 8) & 7) as usize] = mem.read32(address)?;
+            }
+
+            // Load and store single, register offset
+            0b010100..=0b010111 => {
+                let address = self.reg(rn).wrapping_add(self.reg((op >> 6) & 7));
+                let rt = rd as usize;
+                match (op >> 9) & 7 {
+                    0 => mem.write32(address, self.r[rt])?,
+                    1 => mem.write16(address, self.r[rt] as u16)?,
+                    2 => mem.write8(address, self.r[rt] as u8)?,
+                    3 => self.r[rt] = mem.read8(address)? as i8 as u32,
+                    4 => self.r[rt] = mem.read32(address)?,
+                    5 => self.r[rt] = u32::from(mem.read16(address)?),
+                    6 => self.r[rt] = u32::from(mem.read8(address)?),
+                    _ => self.r[rt] = mem.read16(address)? as i16 as u32,
+                }
+            }
+
+            // Load and store single, immediate offset
+            0b011000..=0b100011 => {
+                let imm5 = (op >> 6) & 0x1F;
+                let load = op & 0x800 != 0;
+                let base = self.reg(rn);
+                let rt = rd as usize;
+                match op >> 12 {
+                    0b0110 => {
+                        let address = base.wrapping_add(imm5 * 4);
+                        if load {
+                            self.r[rt] = mem.read32(address)?;
+                        } else {
+                            mem.write32(address, self.r[rt])?;
+                        }
+                    }
+                    0b0111 => {
+                        let address = base.wrapping_add(imm5);
+                        if load {
+                            self.r[rt] = u32::from(mem.read8(address)?);
+                        } else {
+                            mem.write8(address, self.r[rt] as u8)?;
+                        }
+                    }
+                    _ => {
+                        let address = base.wrapping_add(imm5 * 2);
+                        if load {
+                            self.r[rt] = u32::from(mem.read16(address)?);
+                        } else {
+                            mem.write16(address, self.r[rt] as u16)?;
+                        }
+                    }
+                }
+            }
+
+            // Load and store, SP relative
+            0b100100..=0b100111 => {
+                let address = self.r[SP].wrapping_add((op & 0xFF) * 4);
+                let rt = ((op >> 8) & 7) as usize;
+                if op & 0x800 != 0 {
+                    self.r[rt] = mem.read32(address)?;
+                } else {
+                    mem.write32(address, self.r[rt])?;
+                }
+            }
+
+            // ADR
+            0b101000 | 0b101001 => {
+                self.r[((op >> 8) & 7) as usize] = align4(self.pc()) + (op & 0xFF) * 4;
+            }
+
+            // ADD (SP plus immediate)
+            0b101010 | 0b101011 => {
+                self.r[((op >> 8) & 7) as usize] = self.r[SP].wrapping_add((op & 0xFF) * 4);
+            }
+
+            0b101100..=0b101111 => return self.misc16(mem, op),
+
+            // STM
+            0b110000 | 0b110001 => {
+                let rn = ((op >> 8) & 7) as usize;
+                let mut address = self.r[rn];
+                for i in (0..8).filter(|i| op & (1 << i) != 0) {
+                    mem.write32(address, self.r[i])?;
+                    address = address.wrapping_add(4);
+                }
+                self.r[rn] = address;
+            }
+
+            // LDM
+            0b110010 | 0b110011 => {
+                let rn = ((op >> 8) & 7) as usize;
+                let mut address = self.r[rn];
+                for i in (0..8).filter(|i| op & (1 << i) != 0) {
+                    self.r[i] = mem.read32(address)?;
+                    address = address.wrapping_add(4);
+                }
+                if op & (1 << rn) == 0 {
+                    self.r[rn] = address;
+                }
+            }
+
+            // Conditional branch, UDF and SVC
+            0b110100..=0b110111 => {
+                let cond = (op >> 8) & 0xF;
+                if cond >= 0b1110 {
+                    return undefined;
+                }
+                if self.condition_passed(cond) {
+                    let target = self.pc().wrapping_add(sign_extend((op & 0xFF) << 1, 9));
+                    self.branch(target);
+                }
+            }
+
+            // Unconditional branch
+            0b111000 | 0b111001 => {
+                let target = self.pc().wrapping_add(sign_extend((op & 0x7FF) << 1, 12));
+                self.branch(target);
+            }
+
+            _ => return undefined,
+        }
+
+        Ok(())
+    }
+
+    fn misc16(&mut self, mem: &mut Memory, op: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(op));
+        let rd = (op & 7) as usize;
+        let rm = ((op >> 3) & 7) as usize;
+
+        // CBZ and CBNZ
+        if op & 0x500 == 0x100 {
+            let offset = (op >> 3) & 0x40 | (op >> 2) & 0x3E;
+            if (self.r[rd] == 0) != (op & 0x800 != 0) {
+                let target = self.pc().wrapping_add(offset);
+                self.branch(target);
+            }
+            return Ok(());
+        }
+
+        match (op >> 5) & 0x7F {
+            // ADD and SUB (SP plus immediate)
+            0b0000000..=0b0000111 => {
+                let imm = (op & 0x7F) * 4;
+                self.r[SP] = if op & 0x80 != 0 {
+                    self.r[SP].wrapping_sub(imm)
+                } else {
+                    self.r[SP].wrapping_add(imm)
+                };
+            }
+
+            // SXTH, SXTB, UXTH and UXTB
+            0b0010000..=0b0010111 => {
+                let x = self.r[rm];
+                self.r[rd] = match (op >> 6) & 3 {
+                    0 => x as i16 as u32,
+                    1 => x as i8 as u32,
+                    2 => x & 0xFFFF,
+                    _ => x & 0xFF,
+                };
+            }
+
+            // PUSH
+            0b0100000..=0b0101111 => {
+                let list = op & 0xFF | (op & 0x100) << 6;
+                let count = list.count_ones();
+                let mut address = self.r[SP].wrapping_sub(4 * count);
+                self.r[SP] = address;
+                for i in (0..16).filter(|i| list & (1 << i) != 0) {
+                    mem.write32(address, self.r[i])?;
+                    address = address.wrapping_add(4);
+                }
+            }
+
+            // CPS, which has no effect without exceptions
+            0b0110011 => {}
+
+            // REV, REV16 and REVSH
+            0b1010000..=0b1010011 | 0b1010110 | 0b1010111 => {
+                let x = self.r[rm];
+                self.r[rd] = match (op >> 6) & 3 {
+                    0 => x.swap_bytes(),
+                    1 => (x & 0x00FF_00FF) << 8 | (x >> 8) & 0x00FF_00FF,
+                    _ => (x as u16).swap_bytes() as i16 as u32,
+                };
+            }
+
+            // POP
+            0b1100000..=0b1101111 => {
+                let list = op & 0xFF | (op & 0x100) << 7;
+                let mut address = self.r[SP];
+                for i in (0..16).filter(|i| list & (1 << i) != 0) {
+                    let value = mem.read32(address)?;
+                    address = address.wrapping_add(4);
+                    self.set_reg(i, value);
+                }
+                self.r[SP] = address;
+            }
+
+            // IT and hints
+            0b1111000..=0b1111111 => {
+                if op & 0xF != 0 {
+                    self.it = op as u8;
+                }
+            }
+
+            _ => return undefined,
+        }
+
+        Ok(())
+    }
+
+    fn exec32(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        let op2 = (hw1 >> 4) & 0x7F;
+
+        match (hw1 >> 11) & 3 {
+            0b01 if op2 & 0b1100100 == 0 => self.load_store_multiple(mem, hw1, hw2),
+            0b01 if op2 & 0b1100100 == 0b0000100 => self.load_store_dual(mem, hw1, hw2),
+            0b01 if op2 & 0b1100000 == 0b0100000 => self.data_processing_shifted(hw1, hw2),
+            0b01 => self.coprocessor(mem, hw1, hw2),
+            0b10 if hw2 & 0x8000 != 0 => self.branch_misc(hw1, hw2),
+            0b10 if op2 & 0b0100000 == 0 => self.data_processing_imm(hw1, hw2),
+            0b10 => self.plain_binary_imm(hw1, hw2),
+            0b11 if op2 & 0b1110001 == 0 || op2 & 0b1100111 == 0b0000001 => {
+                self.load_store_single(mem, hw1, hw2)
+            }
+            0b11 if op2 & 0b1100111 == 0b0000011 || op2 & 0b1100111 == 0b0000101 => {
+                self.load_store_single(mem, hw1, hw2)
+            }
+            0b11 if op2 & 0b1110000 == 0b0100000 => self.data_processing_reg(hw1, hw2),
+            0b11 if op2 & 0b1111000 == 0b0110000 => self.multiply(hw1, hw2),
+            0b11 if op2 & 0b1111000 == 0b0111000 => self.long_multiply(hw1, hw2),
+            _ => undefined,
+        }
+    }
+
+    fn load_store_multiple(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let rn = (hw1 & 0xF) as usize;
+        let load = hw1 & 0x10 != 0;
+        let writeback = hw1 & 0x20 != 0;
+        let count = hw2.count_ones();
+        let base = self.r[rn];
+
+        let (mut address, end) = match (hw1 >> 7) & 3 {
+            0b01 => (base, base.wrapping_add(4 * count)),
+            0b10 => {
+                let start = base.wrapping_sub(4 * count);
+                (start, start)
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        };
+
+        if !load {
+            for i in (0..16).filter(|i| hw2 & (1 << i) != 0) {
+                mem.write32(address, self.r[i])?;
+                address = address.wrapping_add(4);
+            }
+            if writeback {
+                self.r[rn] = end;
+            }
+            return Ok(());
+        }
+
+        let mut values = [0; 16];
+        for i in (0..16).filter(|i| hw2 & (1 << i) != 0) {
+            values[i] = mem.read32(address)?;
+            address = address.wrapping_add(4);
+        }
+        if writeback && hw2 & (1 << rn) == 0 {
+            self.r[rn] = end;
+        }
+        for i in (0..16).filter(|i| hw2 & (1 << i) != 0) {
+            self.set_reg(i as u32, values[i]);
+        }
+        Ok(())
+    }
+
+    fn load_store_dual(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let op1 = (hw1 >> 7) & 3;
+        let op2 = (hw1 >> 4) & 3;
+        let op3 = (hw2 >> 4) & 0xF;
+        let rn = hw1 & 0xF;
+        let rt = (hw2 >> 12) as usize;
+
+        // Table branches
+        if op1 == 0b01 && op2 == 0b01 && op3 <= 1 {
+            let base = self.reg(rn);
+            let index = self.reg(hw2 & 0xF);
+            let halfwords = if op3 == 0 {
+                u32::from(mem.read8(base.wrapping_add(index))?)
+            } else {
+                u32::from(mem.read16(base.wrapping_add(index << 1))?)
+            };
+            let target = self.pc().wrapping_add(halfwords * 2);
+            self.branch(target);
+            return Ok(());
+        }
+
+        // Exclusive loads and stores, which always succeed as nothing else
+        // runs
+        if op1 & 2 == 0 && op2 & 2 == 0 {
+            let load = op2 & 1 != 0;
+            let base = self.reg(rn);
+            if op1 == 0 {
+                let address = base.wrapping_add((hw2 & 0xFF) * 4);
+                if load {
+                    self.r[rt] = mem.read32(address)?;
+                } else {
+                    mem.write32(address, self.r[rt])?;
+                    self.r[((hw2 >> 8) & 0xF) as usize] = 0;
+                }
+                return Ok(());
+            }
+            let rd = (hw2 & 0xF) as usize;
+            match (load, op3) {
+                (true, 0b0100) => self.r[rt] = u32::from(mem.read8(base)?),
+                (true, 0b0101) => self.r[rt] = u32::from(mem.read16(base)?),
+                (false, 0b0100) => mem.write8(base, self.r[rt] as u8)?,
+                (false, 0b0101) => mem.write16(base, self.r[rt] as u16)?,
+                _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+            }
+            if !load {
+                self.r[rd] = 0;
+            }
+            return Ok(());
+        }
+
+        // LDRD and STRD
+        let index = hw1 & 0x100 != 0;
+        let add = hw1 & 0x80 != 0;
+        let writeback = hw1 & 0x20 != 0;
+        let load = hw1 & 0x10 != 0;
+        let rt2 = ((hw2 >> 8) & 0xF) as usize;
+        let imm = (hw2 & 0xFF) * 4;
+
+        let base = if rn as usize == PC {
+            align4(self.pc())
+        } else {
+            self.r[rn as usize]
+        };
+        let offset = if add {
+            base.wrapping_add(imm)
+        } else {
+            base.wrapping_sub(imm)
+        };
+        let address = if index { offset } else { base };
+
+        if load {
+            self.r[rt] = mem.read32(address)?;
+            self.r[rt2] = mem.read32(address.wrapping_add(4))?;
+        } else {
+            mem.write32(address, self.r[rt])?;
+            mem.write32(address.wrapping_add(4), self.r[rt2])?;
+        }
+        if writeback {
+            self.r[rn as usize] = offset;
+        }
+        Ok(())
+    }
+
+    /// The operations shared by the shifted register and modified immediate
+    /// encodings, returning whether `op` is one of them.
+    fn data_processing(
+        &mut self,
+        op: u32,
+        setflags: bool,
+        rn: u32,
+        rd: u32,
+        operand: u32,
+        carry: bool,
+    ) -> bool {
+        // ORR and ORN with the PC as the first operand are MOV and MVN
+        let x = if rn as usize == PC && matches!(op, 0b0010 | 0b0011) {
+            0
+        } else {
+            self.reg(rn)
+        };
+        let result = match op {
+            0b0000 => self.logical(x & operand, carry, setflags),
+            0b0001 => self.logical(x & !operand, carry, setflags),
+            0b0010 => self.logical(x | operand, carry, setflags),
+            0b0011 => self.logical(x | !operand, carry, setflags),
+            0b0100 => self.logical(x ^ operand, carry, setflags),
+            0b1000 => self.arith(x, operand, false, setflags),
+            0b1010 => self.arith(x, operand, self.c, setflags),
+            0b1011 => self.arith(x, !operand, self.c, setflags),
+            0b1101 => self.arith(x, !operand, true, setflags),
+            0b1110 => self.arith(!x, operand, true, setflags),
+            _ => return false,
+        };
+        // With the PC as the destination these are TST, TEQ, CMN and CMP
+        if rd as usize != PC {
+            self.r[rd as usize] = result;
+        }
+        true
+    }
+
+    fn data_processing_shifted(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let imm5 = (hw2 >> 10) & 0x1C | (hw2 >> 6) & 3;
+        let (kind, amount) = decode_imm_shift((hw2 >> 4) & 3, imm5);
+        let (operand, carry) = shift_c(self.reg(hw2 & 0xF), kind, amount, self.c);
+
+        let op = (hw1 >> 5) & 0xF;
+        let setflags = hw1 & 0x10 != 0;
+        if self.data_processing(op, setflags, hw1 & 0xF, (hw2 >> 8) & 0xF, operand, carry) {
+            Ok(())
+        } else {
+            Err(Trap::Undefined(hw1 << 16 | hw2))
+        }
+    }
+
+    fn data_processing_imm(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let imm12 = (hw1 << 1) & 0x800 | (hw2 >> 4) & 0x700 | hw2 & 0xFF;
+        let (operand, carry) = thumb_expand_imm_c(imm12, self.c);
+
+        let op = (hw1 >> 5) & 0xF;
+        let setflags = hw1 & 0x10 != 0;
+        if self.data_processing(op, setflags, hw1 & 0xF, (hw2 >> 8) & 0xF, operand, carry) {
+            Ok(())
+        } else {
+            Err(Trap::Undefined(hw1 << 16 | hw2))
+        }
+    }
+
+    fn plain_binary_imm(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let rn = hw1 & 0xF;
+        let rd = ((hw2 >> 8) & 0xF) as usize;
+        let imm12 = (hw1 << 1) & 0x800 | (hw2 >> 4) & 0x700 | hw2 & 0xFF;
+        let imm16 = rn << 12 | imm12;
+        let shift = (hw2 >> 10) & 0x1C | (hw2 >> 6) & 3;
+        let width = (hw2 & 0x1F) + 1;
+
+        self.r[rd] = match (hw1 >> 4) & 0x1F {
+            0b00000 if rn as usize == PC => align4(self.pc()).wrapping_add(imm12),
+            0b00000 => self.reg(rn).wrapping_add(imm12),
+            0b00100 => imm16,
+            0b01010 if rn as usize == PC => align4(self.pc()).wrapping_sub(imm12),
+            0b01010 => self.reg(rn).wrapping_sub(imm12),
+            0b01100 => self.r[rd] & 0xFFFF | imm16 << 16,
+            op @ (0b10000 | 0b10010 | 0b11000 | 0b11010) => {
+                let arithmetic = op & 0b00010 != 0;
+                if arithmetic && shift == 0 {
+                    // SSAT16 and USAT16
+                    return Err(Trap::Undefined(hw1 << 16 | hw2));
+                }
+                let value = if arithmetic {
+                    i64::from(self.reg(rn) as i32 >> shift)
+                } else {
+                    i64::from((self.reg(rn) << shift) as i32)
+                };
+                if op & 0b01000 == 0 {
+                    signed_sat(value, width)
+                } else {
+                    unsigned_sat(value, hw2 & 0x1F)
+                }
+            }
+            0b10100 if shift + width <= 32 => sign_extend(self.reg(rn) >> shift, width),
+            0b11100 if shift + width <= 32 => (self.reg(rn) >> shift) & (u32::MAX >> (32 - width)),
+            0b10110 => {
+                let msb = hw2 & 0x1F;
+                if msb < shift {
+                    return Err(Trap::Undefined(hw1 << 16 | hw2));
+                }
+                let mask = (u32::MAX >> (31 - msb + shift)) << shift;
+                let x = if rn as usize == PC { 0 } else { self.reg(rn) };
+                self.r[rd] & !mask | (x << shift) & mask
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        };
+        Ok(())
+    }
+
+    fn branch_misc(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let s = (hw1 >> 10) & 1;
+        let j1 = (hw2 >> 13) & 1;
+        let j2 = (hw2 >> 11) & 1;
+        let imm11 = hw2 & 0x7FF;
+
+        match (hw2 >> 12) & 5 {
+            // Conditional branch, or miscellaneous control
+            0b000 => {
+                let cond = (hw1 >> 6) & 0xF;
+                if cond < 0b1110 {
+                    let imm = s << 20 | j2 << 19 | j1 << 18 | (hw1 & 0x3F) << 12 | imm11 << 1;
+                    if self.condition_passed(cond) {
+                        let target = self.pc().wrapping_add(sign_extend(imm, 21));
+                        self.branch(target);
+                    }
+                    return Ok(());
+                }
+                // Hints, barriers and CLREX have no effect here
+                match (hw1 >> 4) & 0x7F {
+                    0b0111010 | 0b0111011 => Ok(()),
+                    _ => Err(Trap::Undefined(hw1 << 16 | hw2)),
+                }
+            }
+            // B and BL
+            op @ (0b001 | 0b101) => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let imm = s << 24 | i1 << 23 | i2 << 22 | (hw1 & 0x3FF) << 12 | imm11 << 1;
+                let target = self.pc().wrapping_add(sign_extend(imm, 25));
+                if op == 0b101 {
+                    self.r[LR] = self.next | 1;
+                }
+                self.branch(target);
+                Ok(())
+            }
+            _ => Err(Trap::Undefined(hw1 << 16 | hw2)),
+        }
+    }
+
+    fn load_store_single(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        let load = hw1 & 0x10 != 0;
+        let signed = hw1 & 0x100 != 0;
+        let size = 1 << ((hw1 >> 5) & 3);
+        let rn = hw1 & 0xF;
+        let rt = hw2 >> 12;
+        if size == 8 || (signed && !load) {
+            return undefined;
+        }
+
+        let mut writeback = None;
+        let address = if rn as usize == PC {
+            if !load {
+                return undefined;
+            }
+            let base = align4(self.pc());
+            if hw1 & 0x80 != 0 {
+                base.wrapping_add(hw2 & 0xFFF)
+            } else {
+                base.wrapping_sub(hw2 & 0xFFF)
+            }
+        } else if hw1 & 0x80 != 0 {
+            self.r[rn as usize].wrapping_add(hw2 & 0xFFF)
+        } else if hw2 & 0x800 != 0 {
+            let base = self.r[rn as usize];
+            let imm8 = hw2 & 0xFF;
+            let offset = if hw2 & 0x200 != 0 {
+                base.wrapping_add(imm8)
+            } else {
+                base.wrapping_sub(imm8)
+            };
+            if hw2 & 0x100 != 0 {
+                writeback = Some(offset);
+            }
+            if hw2 & 0x400 != 0 {
+                offset
+            } else {
+                base
+            }
+        } else if hw2 & 0xFC0 == 0 {
+            let rm = self.reg(hw2 & 0xF);
+            self.r[rn as usize].wrapping_add(rm << ((hw2 >> 4) & 3))
+        } else {
+            return undefined;
+        };
+
+        if load {
+            // Preload hints
+            if rt as usize == PC && size != 4 {
+                return Ok(());
+            }
+            let value = match (size, signed) {
+                (1, false) => u32::from(mem.read8(address)?),
+                (1, true) => mem.read8(address)? as i8 as u32,
+                (2, false) => u32::from(mem.read16(address)?),
+                (2, true) => mem.read16(address)? as i16 as u32,
+                _ => mem.read32(address)?,
+            };
+            if let Some(offset) = writeback {
+                self.r[rn as usize] = offset;
+            }
+            self.set_reg(rt, value);
+        } else {
+            let value = self.reg(rt);
+            match size {
+                1 => mem.write8(address, value as u8)?,
+                2 => mem.write16(address, value as u16)?,
+                _ => mem.write32(address, value)?,
+            }
+            if let Some(offset) = writeback {
+                self.r[rn as usize] = offset;
+            }
+        }
+        Ok(())
+    }
+
+    fn data_processing_reg(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        let op1 = (hw1 >> 4) & 0xF;
+        let op2 = (hw2 >> 4) & 0xF;
+        let rn = hw1 & 0xF;
+        let rd = ((hw2 >> 8) & 0xF) as usize;
+        let rm = self.reg(hw2 & 0xF);
+
+        self.r[rd] = match (op1, op2) {
+            // LSL, LSR, ASR and ROR (register)
+            (0b0000..=0b0111, 0) => {
+                let (result, carry) = shift_c(self.reg(rn), op1 >> 1, rm & 0xFF, self.c);
+                self.logical(result, carry, op1 & 1 != 0)
+            }
+            // Sign and zero extension, with an optional add
+            (0b0000 | 0b0001 | 0b0100 | 0b0101, 0b1000..=0b1111) => {
+                let x = rm.rotate_right((op2 & 3) * 8);
+                let extended = match op1 {
+                    0b0000 => x as i16 as u32,
+                    0b0001 => x & 0xFFFF,
+                    0b0100 => x as i8 as u32,
+                    _ => x & 0xFF,
+                };
+                if rn as usize == PC {
+                    extended
+                } else {
+                    self.reg(rn).wrapping_add(extended)
+                }
+            }
+            (0b1001, 0b1000) => rm.swap_bytes(),
+            (0b1001, 0b1001) => (rm & 0x00FF_00FF) << 8 | (rm >> 8) & 0x00FF_00FF,
+            (0b1001, 0b1010) => rm.reverse_bits(),
+            (0b1001, 0b1011) => (rm as u16).swap_bytes() as i16 as u32,
+            (0b1011, 0b1000) => rm.leading_zeros(),
+            _ => return undefined,
+        };
+        Ok(())
+    }
+
+    fn multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let n = self.reg(hw1 & 0xF);
+        let m = self.reg(hw2 & 0xF);
+        let ra = hw2 >> 12;
+        let accumulate = if ra as usize == PC { 0 } else { self.reg(ra) };
+        let rd = ((hw2 >> 8) & 0xF) as usize;
+
+        self.r[rd] = match ((hw1 >> 4) & 7, (hw2 >> 4) & 3) {
+            (0b000, 0b00) => accumulate.wrapping_add(n.wrapping_mul(m)),
+            (0b000, 0b01) => accumulate.wrapping_sub(n.wrapping_mul(m)),
+            // SMULxy and SMLAxy, multiplying the bottom or top halves
+            (0b001, op2) => {
+                let x = if op2 & 2 != 0 { n >> 16 } else { n } as i16;
+                let y = if op2 & 1 != 0 { m >> 16 } else { m } as i16;
+                (i32::from(x) * i32::from(y)).wrapping_add(accumulate as i32) as u32
+            }
+            // SMMUL and SMMLA, optionally rounding
+            (0b101, op2) => {
+                let product = i64::from(n as i32) * i64::from(m as i32);
+                let round = if op2 & 1 != 0 { 0x8000_0000 } else { 0 };
+                let result = (i64::from(accumulate as i32) << 32)
+                    .wrapping_add(product)
+                    .wrapping_add(round);
+                (result >> 32) as u32
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        };
+        Ok(())
+    }
+
+    fn long_multiply(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let n = self.reg(hw1 & 0xF);
+        let m = self.reg(hw2 & 0xF);
+        let lo = (hw2 >> 12) as usize;
+        let hi = ((hw2 >> 8) & 0xF) as usize;
+        let accumulated = u64::from(self.r[hi]) << 32 | u64::from(self.r[lo]);
+
+        let result = match ((hw1 >> 4) & 7, (hw2 >> 4) & 0xF) {
+            (0b000, 0b0000) => (i64::from(n as i32) * i64::from(m as i32)) as u64,
+            (0b010, 0b0000) => u64::from(n) * u64::from(m),
+            (0b100, 0b0000) => {
+                accumulated.wrapping_add((i64::from(n as i32) * i64::from(m as i32)) as u64)
+            }
+            (0b110, 0b0000) => accumulated.wrapping_add(u64::from(n) * u64::from(m)),
+            // SDIV and UDIV, where dividing by zero gives zero
+            (0b001, 0b1111) => {
+                self.r[hi] =
+                    (n as i32)
+                        .checked_div(m as i32)
+                        .unwrap_or(if m == 0 { 0 } else { i32::MIN }) as u32;
+                return Ok(());
+            }
+            (0b011, 0b1111) => {
+                self.r[hi] = n.checked_div(m).unwrap_or(0);
+                return Ok(());
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        };
+
+        self.r[lo] = result as u32;
+        self.r[hi] = (result >> 32) as u32;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: u32 = 0x2000_0000;
+
+    fn run(code: &[u16], setup: impl FnOnce(&mut Cpu)) -> Cpu {
+        let mut mem = Memory::default();
+        mem.map(CODE, 0x100, true);
+        let bytes: Vec<u8> = code.iter().flat_map(|h| h.to_le_bytes()).collect();
+        mem.load(CODE, &bytes).unwrap();
+
+        let mut cpu = Cpu::default();
+        cpu.r[PC] = CODE;
+        cpu.r[SP] = CODE + 0x100;
+        setup(&mut cpu);
+        while cpu.r[PC] < CODE + bytes.len() as u32 {
+            cpu.step(&mut mem).unwrap();
+        }
+        cpu
+    }
+
+    #[test]
+    fn it_block_skips_failed_conditions() {
+        // cmp r0, #5; ite eq; moveq r1, #1; movne r1, #2; adds r2, r1, #0
+        let cpu = run(&[0x2805, 0xbf0c, 0x2101, 0x2102, 0x1c0a], |cpu| {
+            cpu.r[0] = 5
+        });
+        assert_eq!((cpu.r[1], cpu.r[2]), (1, 1));
+
+        let cpu = run(&[0x2805, 0xbf0c, 0x2101, 0x2102, 0x1c0a], |cpu| {
+            cpu.r[0] = 4
+        });
+        assert_eq!((cpu.r[1], cpu.r[2]), (2, 2));
+    }
+
+    #[test]
+    fn wide_immediates_and_bitfields() {
+        // movw r0, #0x1234; movt r0, #0x8765; ubfx r1, r0, #4, #8;
+        // sbfx r2, r0, #28, #4; bfi r0, r1, #0, #4
+        let cpu = run(
+            &[
+                0xf241, 0x2034, 0xf2c8, 0x7065, 0xf3c0, 0x1107, 0xf340, 0x7203, 0xf361, 0x0003,
+            ],
+            |_| {},
+        );
+        assert_eq!(cpu.r[1], 0x23);
+        assert_eq!(cpu.r[2], 0xFFFF_FFF8);
+        assert_eq!(cpu.r[0], 0x8765_1233);
+    }
+
+    #[test]
+    fn subtraction_sets_carry_as_not_borrow() {
+        // subs r0, r0, r1; sbc.w r2, r2, r3
+        let cpu = run(&[0x1a40, 0xeb62, 0x0203], |cpu| {
+            cpu.r[0] = 1;
+            cpu.r[1] = 2;
+            cpu.r[2] = 10;
+            cpu.r[3] = 3;
+        });
+        assert_eq!(cpu.r[0], u32::MAX);
+        assert!(cpu.n && !cpu.c);
+        assert_eq!(cpu.r[2], 6);
+    }
+}
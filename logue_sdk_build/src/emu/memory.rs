@@ -0,0 +1,86 @@
+//! The emulated address space: a few flat regions, little-endian like the
+//! Cortex-M4.
+
+/// An access outside every region, or a write to a read-only one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub address: u32,
+    pub write: bool,
+}
+
+struct Region {
+    base: u32,
+    data: Vec<u8>,
+    writable: bool,
+}
+
+#[derive(Default)]
+pub struct Memory {
+    regions: Vec<Region>,
+}
+
+impl Memory {
+    pub fn map(&mut self, base: u32, size: usize, writable: bool) {
+        self.regions.push(Region {
+            base,
+            data: vec![0; size],
+            writable,
+        });
+    }
+
+    fn region(&mut self, address: u32, len: usize, write: bool) -> Result<&mut [u8], Fault> {
+        let fault = Fault { address, write };
+        let region = self
+            .regions
+            .iter_mut()
+            .find(|r| address >= r.base && address - r.base < r.data.len() as u32)
+            .ok_or(fault)?;
+        if write && !region.writable {
+            return Err(fault);
+        }
+        let offset = (address - region.base) as usize;
+        region.data.get_mut(offset..offset + len).ok_or(fault)
+    }
+
+    /// Fill memory at `address` regardless of whether it's writable, for
+    /// loading the payload and firmware data.
+    pub fn load(&mut self, address: u32, data: &[u8]) -> Result<(), Fault> {
+        self.region(address, data.len(), false)?
+            .copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn read<const N: usize>(&mut self, address: u32) -> Result<[u8; N], Fault> {
+        let bytes = self.region(address, N, false)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    pub fn write<const N: usize>(&mut self, address: u32, value: [u8; N]) -> Result<(), Fault> {
+        self.region(address, N, true)?.copy_from_slice(&value);
+        Ok(())
+    }
+
+    pub fn read8(&mut self, address: u32) -> Result<u8, Fault> {
+        Ok(self.read::<1>(address)?[0])
+    }
+
+    pub fn read16(&mut self, address: u32) -> Result<u16, Fault> {
+        self.read(address).map(u16::from_le_bytes)
+    }
+
+    pub fn read32(&mut self, address: u32) -> Result<u32, Fault> {
+        self.read(address).map(u32::from_le_bytes)
+    }
+
+    pub fn write8(&mut self, address: u32, value: u8) -> Result<(), Fault> {
+        self.write(address, [value])
+    }
+
+    pub fn write16(&mut self, address: u32, value: u16) -> Result<(), Fault> {
+        self.write(address, value.to_le_bytes())
+    }
+
+    pub fn write32(&mut self, address: u32, value: u32) -> Result<(), Fault> {
+        self.write(address, value.to_le_bytes())
+    }
+}
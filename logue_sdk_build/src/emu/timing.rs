@@ -0,0 +1,121 @@
+//! Instruction timings from the Cortex-M4 Technical Reference Manual, for
+//! estimating how long a hook takes on the device.
+//!
+//! Memory is taken to have no wait states, which holds for the SRAM a unit
+//! runs from, and loads and stores aren't pipelined with their neighbours, so
+//! the estimate errs on the slow side.
+
+/// Cycles to refill the pipeline after a branch, which the manual gives as 1
+/// to 3 depending on the target's alignment and width.
+pub const BRANCH_REFILL: u32 = 2;
+
+/// Division stops early when the operands are close in size, taking 2 to 12
+/// cycles. The worst case is counted.
+const DIVIDE: u32 = 12;
+
+/// Cycles taken by an executed instruction, not counting any branch.
+pub fn cycles(hw1: u32, hw2: u32, wide: bool) -> u32 {
+    if wide {
+        cycles32(hw1, hw2)
+    } else {
+        cycles16(hw1)
+    }
+}
+
+fn cycles16(op: u32) -> u32 {
+    match op >> 12 {
+        // Loads and stores, by register, immediate or SP offset
+        0b0101..=0b1001 => 2,
+        0b0100 if op & 0x0800 != 0 => 2,
+        // LDM and STM
+        0b1100 => 1 + (op & 0xFF).count_ones(),
+        // PUSH and POP
+        0b1011 if op & 0x0600 == 0x0400 => 1 + (op & 0x1FF).count_ones(),
+        _ => 1,
+    }
+}
+
+fn cycles32(hw1: u32, hw2: u32) -> u32 {
+    let op2 = (hw1 >> 4) & 0x7F;
+    match (hw1 >> 11) & 3 {
+        0b01 if op2 & 0b1100100 == 0 => 1 + (hw2 & 0xFFFF).count_ones(),
+        0b01 if op2 & 0b1100100 == 0b0000100 => {
+            // TBB and TBH and the exclusives take 2, LDRD and STRD 3
+            let table_branch =
+                (hw1 >> 7) & 3 == 0b01 && (hw1 >> 4) & 3 == 0b01 && (hw2 >> 4) & 0xF <= 1;
+            let exclusive = (hw1 >> 7) & 2 == 0 && (hw1 >> 5) & 1 == 0;
+            if table_branch || exclusive {
+                2
+            } else {
+                3
+            }
+        }
+        0b01 if op2 & 0b1100000 == 0b0100000 => 1,
+        0b01 => vfp_cycles(hw1, hw2),
+        0b11 if op2 & 0b1110001 == 0 || matches!(op2 & 0b1100111, 0b001 | 0b011 | 0b101) => 2,
+        // MLA, MLS and the other accumulating multiplies
+        0b11 if op2 & 0b1111000 == 0b0110000 && hw2 >> 12 != 0xF => 2,
+        // SDIV and UDIV
+        0b11 if op2 & 0b1111000 == 0b0111000 && (hw1 >> 4) & 5 == 1 => DIVIDE,
+        _ => 1,
+    }
+}
+
+fn vfp_cycles(hw1: u32, hw2: u32) -> u32 {
+    match (hw1 >> 4) & 0x3F {
+        // VMOV between two core registers and two singles
+        0b000100 | 0b000101 => 2,
+        op1 if op1 & 0b100000 == 0 => {
+            let index = hw1 & 0x100 != 0;
+            let writeback = hw1 & 0x20 != 0;
+            if index && !writeback {
+                // VLDR and VSTR
+                2
+            } else {
+                1 + (hw2 & 0xFF)
+            }
+        }
+        op1 if op1 & 0b110000 == 0b100000 && hw2 & 0x10 == 0 => {
+            match (hw1 >> 5) & 4 | (hw1 >> 4) & 3 {
+                // VMLA, VMLS, VNMLA, VNMLS and the fused versions
+                0b000 | 0b001 | 0b101 | 0b110 => 3,
+                // VDIV
+                0b100 => 14,
+                // VSQRT
+                0b111 if hw1 & 0xF == 0b0001 && hw2 & 0xC0 == 0xC0 => 14,
+                _ => 1,
+            }
+        }
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_and_multiples() {
+        // ldr r0, [r1, #4]; push {r4-r7, lr}; ldmia.w r0, {r1-r4}
+        assert_eq!(cycles(0x6848, 0, false), 2);
+        assert_eq!(cycles(0xb5f0, 0, false), 6);
+        assert_eq!(cycles(0xe890, 0x001e, true), 5);
+        // vldr s0, [r3, #276]; vpush {s16-s19}
+        assert_eq!(cycles(0xed93, 0x0a45, true), 2);
+        assert_eq!(cycles(0xed2d, 0x8a04, true), 5);
+    }
+
+    #[test]
+    fn arithmetic() {
+        // mla r0, r1, r2, r3; mul r0, r1, r2; udiv r0, r1, r2
+        assert_eq!(cycles(0xfb01, 0x3002, true), 2);
+        assert_eq!(cycles(0xfb01, 0xf002, true), 1);
+        assert_eq!(cycles(0xfbb1, 0xf0f2, true), DIVIDE);
+        // vmla.f32 s0, s1, s2; vmul.f32 s0, s1, s2; vdiv.f32 s0, s1, s2;
+        // vsqrt.f32 s0, s1
+        assert_eq!(cycles(0xee00, 0x0a81, true), 3);
+        assert_eq!(cycles(0xee20, 0x0a81, true), 1);
+        assert_eq!(cycles(0xee80, 0x0a81, true), 14);
+        assert_eq!(cycles(0xeeb1, 0x0ae0, true), 14);
+    }
+}
@@ -0,0 +1,310 @@
+//! The Cortex-M4's single precision FPU. Arithmetic follows IEEE 754 with the
+//! reset FPSCR: round to nearest, no flush to zero, so results match `f32` on
+//! the host bit for bit.
+
+use super::cpu::{Cpu, Trap, PC};
+use super::memory::Memory;
+
+fn vfp_expand_imm(imm8: u32) -> u32 {
+    let b6 = (imm8 >> 6) & 1;
+    (imm8 >> 7) << 31
+        | (b6 ^ 1) << 30
+        | if b6 != 0 { 0x1F << 25 } else { 0 }
+        | ((imm8 >> 4) & 3) << 23
+        | (imm8 & 0xF) << 19
+}
+
+fn compare(x: f32, y: f32) -> u32 {
+    if x.is_nan() || y.is_nan() {
+        0b0011
+    } else if x == y {
+        0b0110
+    } else if x < y {
+        0b1000
+    } else {
+        0b0010
+    }
+}
+
+/// Float to integer with saturation, as `vcvt` does. Rust's `as` saturates
+/// the same way, including NaN to zero.
+fn to_int(x: f32, signed: bool, bits: u32) -> u32 {
+    if signed {
+        let min = -(1i64 << (bits - 1));
+        (x as i64).clamp(min, -min - 1) as u32
+    } else {
+        (x as u64).min((1u64 << bits) - 1) as u32
+    }
+}
+
+impl Cpu {
+    fn f(&self, i: usize) -> f32 {
+        f32::from_bits(self.s[i])
+    }
+
+    fn set_f(&mut self, i: usize, x: f32) {
+        self.s[i] = x.to_bits();
+    }
+
+    /// Coprocessor instructions, of which only the FPU's exist.
+    pub(super) fn coprocessor(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        if hw1 & 0x1000 != 0 || (hw2 >> 9) & 7 != 0b101 {
+            return undefined;
+        }
+
+        match (hw1 >> 4) & 0x3F {
+            0b000000 | 0b000001 => undefined,
+            0b000100 | 0b000101 => self.vmov64(hw1, hw2),
+            op1 if op1 & 0b100000 == 0 => self.vload_store(mem, hw1, hw2),
+            op1 if op1 & 0b110000 == 0b100000 && hw2 & 0x10 == 0 => self.vdata(hw1, hw2),
+            op1 if op1 & 0b110000 == 0b100000 => self.vtransfer(hw1, hw2),
+            _ => undefined,
+        }
+    }
+
+    /// VMOV between two core registers and two singles or a double.
+    fn vmov64(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let to_core = hw1 & 0x10 != 0;
+        let rt = (hw2 >> 12) as usize;
+        let rt2 = (hw1 & 0xF) as usize;
+        let m = (hw2 >> 5) & 1;
+        let vm = hw2 & 0xF;
+        let s = if hw2 & 0x100 != 0 {
+            (m << 4 | vm) * 2
+        } else {
+            vm << 1 | m
+        } as usize;
+        if s == 31 || rt == PC || rt2 == PC {
+            return Err(Trap::Undefined(hw1 << 16 | hw2));
+        }
+
+        if to_core {
+            self.r[rt] = self.s[s];
+            self.r[rt2] = self.s[s + 1];
+        } else {
+            self.s[s] = self.r[rt];
+            self.s[s + 1] = self.r[rt2];
+        }
+        Ok(())
+    }
+
+    /// VLDR, VSTR, VLDM, VSTM, VPUSH and VPOP.
+    fn vload_store(&mut self, mem: &mut Memory, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        let index = hw1 & 0x100 != 0;
+        let add = hw1 & 0x80 != 0;
+        let d = (hw1 >> 6) & 1;
+        let writeback = hw1 & 0x20 != 0;
+        let load = hw1 & 0x10 != 0;
+        let rn = (hw1 & 0xF) as usize;
+        let vd = (hw2 >> 12) & 0xF;
+        let double = hw2 & 0x100 != 0;
+        let imm = (hw2 & 0xFF) * 4;
+
+        let first = if double {
+            (d << 4 | vd) * 2
+        } else {
+            vd << 1 | d
+        } as usize;
+        let (address, words) = if index && !writeback {
+            let base = if rn == PC {
+                (self.r[PC] + 4) & !3
+            } else {
+                self.r[rn]
+            };
+            let address = if add {
+                base.wrapping_add(imm)
+            } else {
+                base.wrapping_sub(imm)
+            };
+            (address, if double { 2 } else { 1 })
+        } else {
+            if index == add || rn == PC {
+                return undefined;
+            }
+            let base = self.r[rn];
+            let end = if add {
+                base.wrapping_add(imm)
+            } else {
+                base.wrapping_sub(imm)
+            };
+            if writeback {
+                self.r[rn] = end;
+            }
+            (if add { base } else { end }, imm as usize / 4)
+        };
+        if first + words > self.s.len() {
+            return undefined;
+        }
+
+        for (i, s) in (first..first + words).enumerate() {
+            let address = address.wrapping_add(4 * i as u32);
+            if load {
+                self.s[s] = mem.read32(address)?;
+            } else {
+                mem.write32(address, self.s[s])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfers between a core register and a single, half a double or a
+    /// system register.
+    fn vtransfer(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let to_core = hw1 & 0x10 != 0;
+        let rt = (hw2 >> 12) as usize;
+        let vn = hw1 & 0xF;
+        let n = (hw2 >> 7) & 1;
+
+        match ((hw1 >> 5) & 7, hw2 & 0x100 != 0) {
+            (0b000, false) if rt != PC => {
+                let s = (vn << 1 | n) as usize;
+                if to_core {
+                    self.r[rt] = self.s[s];
+                } else {
+                    self.s[s] = self.r[rt];
+                }
+            }
+            // VMRS and VMSR, of the FPSCR only
+            (0b111, false) if vn == 1 => match (to_core, rt == PC) {
+                (true, true) => self.set_nzcv(self.fpscr >> 28),
+                (true, false) => self.r[rt] = self.fpscr,
+                (false, false) => self.fpscr = self.r[rt],
+                (false, true) => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+            },
+            // VMOV of either half of a double
+            (0b000 | 0b001, true) if hw2 & 0x60 == 0 && rt != PC => {
+                let s = ((n << 4 | vn) * 2 + ((hw1 >> 5) & 1)) as usize;
+                if to_core {
+                    self.r[rt] = self.s[s];
+                } else {
+                    self.s[s] = self.r[rt];
+                }
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        }
+        Ok(())
+    }
+
+    fn vdata(&mut self, hw1: u32, hw2: u32) -> Result<(), Trap> {
+        let undefined = Err(Trap::Undefined(hw1 << 16 | hw2));
+        // Only single precision exists on the M4
+        if hw2 & 0x100 != 0 {
+            return undefined;
+        }
+
+        let vn = hw1 & 0xF;
+        let vm = hw2 & 0xF;
+        let op = hw2 & 0x40 != 0;
+        let sd = (((hw2 >> 11) & 0x1E) | (hw1 >> 6) & 1) as usize;
+        let sn = (vn << 1 | (hw2 >> 7) & 1) as usize;
+        let sm = (vm << 1 | (hw2 >> 5) & 1) as usize;
+        let (d, n, m) = (self.f(sd), self.f(sn), self.f(sm));
+
+        let result = match (hw1 >> 5) & 4 | (hw1 >> 4) & 3 {
+            0b000 if !op => d + n * m,
+            0b000 => d - n * m,
+            0b001 if !op => -d + n * m,
+            0b001 => -d - n * m,
+            0b010 if !op => n * m,
+            0b010 => -(n * m),
+            0b011 if !op => n + m,
+            0b011 => n - m,
+            0b100 if !op => n / m,
+            0b101 if !op => n.mul_add(m, -d),
+            0b101 => (-n).mul_add(m, -d),
+            0b110 if !op => n.mul_add(m, d),
+            0b110 => (-n).mul_add(m, d),
+            0b111 => return self.vdata_other(hw1, hw2, sd, sm),
+            _ => return undefined,
+        };
+        self.set_f(sd, result);
+        Ok(())
+    }
+
+    fn vdata_other(&mut self, hw1: u32, hw2: u32, sd: usize, sm: usize) -> Result<(), Trap> {
+        let opc2 = hw1 & 0xF;
+        let op = hw2 & 0x80 != 0;
+
+        // VMOV (immediate)
+        if hw2 & 0x40 == 0 {
+            self.s[sd] = vfp_expand_imm(opc2 << 4 | hw2 & 0xF);
+            return Ok(());
+        }
+
+        let m = self.f(sm);
+        match opc2 {
+            0b0000 if !op => self.s[sd] = self.s[sm],
+            0b0000 => self.s[sd] = self.s[sm] & !(1 << 31),
+            0b0001 if !op => self.s[sd] = self.s[sm] ^ 1 << 31,
+            0b0001 => self.set_f(sd, m.sqrt()),
+            // VCMP and VCMPE, against zero for 0b0101
+            0b0100 | 0b0101 => {
+                let y = if opc2 & 1 != 0 { 0.0 } else { m };
+                let nzcv = compare(self.f(sd), y);
+                self.fpscr = self.fpscr & 0x0FFF_FFFF | nzcv << 28;
+            }
+            // Integer to float
+            0b1000 if op => self.set_f(sd, self.s[sm] as i32 as f32),
+            0b1000 => self.set_f(sd, self.s[sm] as f32),
+            // Float to integer, rounding towards zero or to nearest for VCVTR
+            0b1100 | 0b1101 => {
+                let x = if op { m } else { m.round_ties_even() };
+                self.s[sd] = to_int(x, opc2 & 1 != 0, 32);
+            }
+            // Between float and fixed point, in place
+            0b1010 | 0b1011 | 0b1110 | 0b1111 => {
+                let to_fixed = opc2 & 0b100 != 0;
+                let signed = opc2 & 1 == 0;
+                let size = if op { 32 } else { 16 };
+                let frac_bits = size as i32 - ((hw2 & 0xF) << 1 | (hw2 >> 5) & 1) as i32;
+                if frac_bits < 0 {
+                    return Err(Trap::Undefined(hw1 << 16 | hw2));
+                }
+                let scale = 2f64.powi(frac_bits);
+
+                if to_fixed {
+                    let x = (f64::from(self.f(sd)) * scale) as f32;
+                    let fixed = to_int(x, signed, size);
+                    self.s[sd] = match (signed, size) {
+                        (true, 16) => fixed as i16 as u32,
+                        _ => fixed,
+                    };
+                } else {
+                    let x = self.s[sd];
+                    let fixed = match (signed, size) {
+                        (true, 16) => f64::from(x as i16),
+                        (false, 16) => f64::from(x as u16),
+                        (true, _) => f64::from(x as i32),
+                        (false, _) => f64::from(x),
+                    };
+                    self.set_f(sd, (fixed / scale) as f32);
+                }
+            }
+            _ => return Err(Trap::Undefined(hw1 << 16 | hw2)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_immediates() {
+        assert_eq!(f32::from_bits(vfp_expand_imm(0x70)), 1.0);
+        assert_eq!(f32::from_bits(vfp_expand_imm(0x60)), 0.5);
+        assert_eq!(f32::from_bits(vfp_expand_imm(0xF8)), -1.5);
+        assert_eq!(f32::from_bits(vfp_expand_imm(0x00)), 2.0);
+    }
+
+    #[test]
+    fn converts_with_saturation() {
+        assert_eq!(to_int(-1.5, false, 32), 0);
+        assert_eq!(to_int(3e9, true, 32), i32::MAX as u32);
+        assert_eq!(to_int(f32::NAN, true, 32), 0);
+        assert_eq!(to_int(-40000.0, true, 16), -32768i32 as u32);
+    }
+}
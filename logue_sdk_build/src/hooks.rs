@@ -0,0 +1,217 @@
+//! Decoding and checking the hook table at the start of a v1 payload.
+//!
+//! The tables are read from the bytes directly rather than through the SDK's
+//! `#[repr(C)]` structs, since function pointers are wider on the host, but
+//! the header offsets and hook order come from those structs.
+
+use std::fmt;
+use std::ops::Range;
+
+use logue_sdk::delfx::UserDelFxHookTable;
+use logue_sdk::manifest::Module;
+use logue_sdk::modfx::UserModFxHookTable;
+use logue_sdk::oscapi::{Platform, UserOscHookTable};
+use logue_sdk::revfx::UserRevFxHookTable;
+
+use crate::payload::{self, HooksHeader, Payload, HOOKS_HEADER_SIZE};
+
+/// Where user units are loaded, and so where a bare `payload.bin` starts.
+pub const LOAD_ADDRESS: u32 = 0x2000_0000;
+
+/// The function pointers following the header, in table order.
+pub fn hook_names(module: Module) -> &'static [&'static str] {
+    match module {
+        Module::Osc => &UserOscHookTable::HOOK_NAMES,
+        Module::ModFx => &UserModFxHookTable::HOOK_NAMES,
+        Module::DelFx => &UserDelFxHookTable::HOOK_NAMES,
+        Module::RevFx => &UserRevFxHookTable::HOOK_NAMES,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Hook {
+    pub name: &'static str,
+    pub address: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct HookTable {
+    pub header: HooksHeader,
+    pub hooks: Vec<Hook>,
+}
+
+impl HookTable {
+    pub fn parse(data: &[u8]) -> Result<Self, payload::Error> {
+        let header = HooksHeader::parse(data)?;
+        let names = hook_names(header.module);
+
+        let table = data
+            .get(HOOKS_HEADER_SIZE..HOOKS_HEADER_SIZE + 4 * names.len())
+            .ok_or(payload::Error::Truncated)?;
+        let hooks = names
+            .iter()
+            .zip(table.chunks_exact(4))
+            .map(|(&name, b)| Hook {
+                name,
+                address: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            })
+            .collect();
+
+        Ok(Self { header, hooks })
+    }
+
+    /// Size of the table in the payload.
+    pub fn size(&self) -> u32 {
+        (HOOKS_HEADER_SIZE + 4 * self.hooks.len()) as u32
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    Platform {
+        expected: Platform,
+        actual: Platform,
+    },
+    NotThumb {
+        hook: &'static str,
+        address: u32,
+    },
+    OutsideText {
+        hook: &'static str,
+        address: u32,
+        text: Range<u32>,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Platform { expected, actual } => write!(
+                f,
+                "built for {}, expected {}",
+                actual.name(),
+                expected.name()
+            ),
+            Problem::NotThumb { hook, address } => {
+                write!(f, "{hook} hook {address:#010x} is missing the Thumb bit")
+            }
+            Problem::OutsideText {
+                hook,
+                address,
+                text,
+            } => write!(
+                f,
+                "{hook} hook {address:#010x} is outside the code at {:#010x}..{:#010x}",
+                text.start, text.end
+            ),
+        }
+    }
+}
+
+/// Check that every hook is a Thumb function pointer into `text`, and that
+/// the table is for `platform` if given.
+pub fn check(table: &HookTable, text: Range<u32>, platform: Option<Platform>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    if let Some(expected) = platform {
+        if table.header.platform != expected {
+            problems.push(Problem::Platform {
+                expected,
+                actual: table.header.platform,
+            });
+        }
+    }
+
+    for hook in &table.hooks {
+        if hook.address & 1 == 0 {
+            problems.push(Problem::NotThumb {
+                hook: hook.name,
+                address: hook.address,
+            });
+        }
+        if !text.contains(&(hook.address & !1)) {
+            problems.push(Problem::OutsideText {
+                hook: hook.name,
+                address: hook.address,
+                text: text.clone(),
+            });
+        }
+    }
+
+    problems
+}
+
+/// Where the code of a linked unit is: its `.text` section.
+pub fn elf_text(payload: &Payload) -> Option<Range<u32>> {
+    payload
+        .sections
+        .iter()
+        .find(|s| s.name == ".text")
+        .map(|s| s.address..s.end())
+}
+
+/// Without sections, the best a bare `payload.bin` can say is that code is
+/// somewhere after the hook table.
+pub fn bin_text(data: &[u8], table: &HookTable) -> Range<u32> {
+    LOAD_ADDRESS + table.size()..LOAD_ADDRESS + data.len() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logue_sdk::oscapi::{API_VERSION, HOOKS_MAGIC};
+
+    fn osc_table(platform: u8, hooks: [u32; 7]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(HOOKS_MAGIC);
+        data.extend(API_VERSION.to_le_bytes());
+        data.push(platform);
+        data.extend([0; 7]);
+        for h in hooks {
+            data.extend(h.to_le_bytes());
+        }
+        data.resize(0x100, 0);
+        data
+    }
+
+    #[test]
+    fn valid_table_has_no_problems() {
+        let data = osc_table(2, [0x2000_0041; 7]);
+        let table = HookTable::parse(&data).unwrap();
+        assert_eq!(table.header.module, Module::Osc);
+        assert_eq!(table.hooks[1].name, "cycle");
+
+        let text = bin_text(&data, &table);
+        assert_eq!(check(&table, text, Some(Platform::MinilogueXD)), []);
+    }
+
+    #[test]
+    fn reports_each_bad_hook() {
+        let mut hooks = [0x2000_0041; 7];
+        hooks[0] = 0x2000_0040;
+        hooks[6] = 0x0800_0001;
+        let data = osc_table(1, hooks);
+        let table = HookTable::parse(&data).unwrap();
+        let text = bin_text(&data, &table);
+
+        let problems = check(&table, text.clone(), Some(Platform::MinilogueXD));
+        assert_eq!(
+            problems,
+            [
+                Problem::Platform {
+                    expected: Platform::MinilogueXD,
+                    actual: Platform::Prologue,
+                },
+                Problem::NotThumb {
+                    hook: "entry",
+                    address: 0x2000_0040,
+                },
+                Problem::OutsideText {
+                    hook: "param",
+                    address: 0x0800_0001,
+                    text,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! Reading unit archives back, and checking that their manifest and payload
+//! agree with each other and with what the SDK knows of the platforms.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+use logue_sdk::manifest::{Module, ParamUnit, MAX_PARAMS};
+use logue_sdk::oscapi::Platform;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use crate::hooks::{self, HookTable};
+use crate::pack::{archive_extension, check_manifest, Error};
+use crate::parse_version;
+use crate::payload::{self, api_is_compatible};
+
+/// The two files of a unit archive, as stored.
+pub struct Archive {
+    /// The directory the files are in, which is the unit's name.
+    pub dir: String,
+    pub manifest: String,
+    pub payload: Vec<u8>,
+}
+
+/// Whether `dir` names a single directory, so that extracting into it stays
+/// inside the destination.
+fn is_unit_dir(dir: &str) -> bool {
+    let mut components = Path::new(dir).components();
+    matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+        && !dir.contains(['/', '\\'])
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, Error> {
+    let mut entry = zip
+        .by_name(name)
+        .map_err(|_| Error::Archive(format!("no {name}")))?;
+    if entry.enclosed_name().is_none() {
+        return Err(Error::Archive(format!("unsafe path {name}")));
+    }
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut zip = ZipArchive::new(File::open(path)?)?;
+
+        let manifest_path = zip
+            .file_names()
+            .find(|n| n.ends_with("/manifest.json") && n.matches('/').count() == 1)
+            .ok_or_else(|| Error::Archive("no <name>/manifest.json".into()))?
+            .to_owned();
+        let dir = manifest_path.trim_end_matches("/manifest.json").to_owned();
+        if !is_unit_dir(&dir) {
+            return Err(Error::Archive(format!("invalid directory \"{dir}\"")));
+        }
+
+        let manifest = String::from_utf8(read_entry(&mut zip, &manifest_path)?)
+            .map_err(|_| Error::Manifest("not UTF-8".into()))?;
+        let payload = read_entry(&mut zip, &format!("{dir}/payload.bin"))?;
+
+        Ok(Self {
+            dir,
+            manifest,
+            payload,
+        })
+    }
+
+    /// Write the files out under `dir`, as `<name>/manifest.json` and
+    /// `<name>/payload.bin`.
+    pub fn extract(&self, dir: &Path) -> io::Result<()> {
+        if !is_unit_dir(&self.dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid directory \"{}\"", self.dir),
+            ));
+        }
+        let unit = dir.join(&self.dir);
+        fs::create_dir_all(&unit)?;
+        fs::write(unit.join("manifest.json"), &self.manifest)?;
+        fs::write(unit.join("payload.bin"), &self.payload)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestParam {
+    pub name: String,
+    pub min: i64,
+    pub max: i64,
+    pub unit: ParamUnit,
+}
+
+/// The header of a `manifest.json`, as read rather than declared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestHeader {
+    pub platform: Platform,
+    pub module: Module,
+    pub api: u32,
+    pub dev_id: u64,
+    pub prg_id: u64,
+    pub version: u32,
+    pub name: String,
+    pub params: Vec<ManifestParam>,
+}
+
+fn manifest_error(e: impl fmt::Display) -> Error {
+    Error::Manifest(e.to_string())
+}
+
+fn field<'a, T>(
+    header: &'a Value,
+    key: &str,
+    get: impl FnOnce(&'a Value) -> Option<T>,
+) -> Result<T, Error> {
+    get(&header[key]).ok_or_else(|| manifest_error(format!("missing or invalid \"{key}\"")))
+}
+
+fn parse_param(i: usize, param: &Value) -> Result<ManifestParam, Error> {
+    let invalid = || manifest_error(format!("invalid parameter {}", i + 1));
+    let [name, min, max, unit] = param.as_array().map(Vec::as_slice).ok_or_else(invalid)? else {
+        return Err(invalid());
+    };
+
+    let param = ManifestParam {
+        name: name.as_str().ok_or_else(invalid)?.to_owned(),
+        min: min.as_i64().ok_or_else(invalid)?,
+        max: max.as_i64().ok_or_else(invalid)?,
+        unit: ParamUnit::from_name(unit.as_str().ok_or_else(invalid)?).ok_or_else(invalid)?,
+    };
+    if param.min > param.max {
+        return Err(manifest_error(format!(
+            "parameter {} minimum {} is above its maximum {}",
+            i + 1,
+            param.min,
+            param.max
+        )));
+    }
+    Ok(param)
+}
+
+impl ManifestHeader {
+    pub fn parse(manifest: &str) -> Result<Self, Error> {
+        let json: Value = serde_json::from_str(manifest).map_err(manifest_error)?;
+        let header = &json["header"];
+
+        let platform = field(header, "platform", |v| Platform::from_name(v.as_str()?))?;
+        let module = field(header, "module", |v| Module::from_name(v.as_str()?))?;
+        let api = field(header, "api", |v| parse_version(v.as_str()?))?;
+        if !api_is_compatible(api) {
+            return Err(manifest_error(payload::Error::Api(api)));
+        }
+
+        let params = field(header, "params", Value::as_array)?;
+        let num_param = field(header, "num_param", Value::as_u64)?;
+        if num_param != params.len() as u64 {
+            return Err(manifest_error(format!(
+                "\"num_param\" is {num_param}, but there are {} parameters",
+                params.len()
+            )));
+        }
+        if params.len() > MAX_PARAMS {
+            return Err(manifest_error(format!(
+                "{} parameters, at most {MAX_PARAMS} are supported",
+                params.len()
+            )));
+        }
+
+        Ok(Self {
+            platform,
+            module,
+            api,
+            dev_id: field(header, "dev_id", Value::as_u64)?,
+            prg_id: field(header, "prg_id", Value::as_u64)?,
+            version: field(header, "version", |v| parse_version(v.as_str()?))?,
+            name: field(header, "name", |v| Some(v.as_str()?.to_owned()))?,
+            params: params
+                .iter()
+                .enumerate()
+                .map(|(i, p)| parse_param(i, p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A unit archive whose manifest and hook table could be read.
+pub struct Inspection {
+    pub archive: Archive,
+    pub manifest: ManifestHeader,
+    pub hooks: HookTable,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The archive's extension is for a different platform than its manifest.
+    Extension {
+        actual: String,
+        expected: &'static str,
+    },
+    /// The directory in the archive isn't the unit's name.
+    Dir {
+        dir: String,
+        name: String,
+    },
+    /// The manifest doesn't describe the payload.
+    Manifest(String),
+    Hooks(hooks::Problem),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Extension { actual, expected } => write!(
+                f,
+                "extension is .{actual}, but the manifest is for .{expected}"
+            ),
+            Problem::Dir { dir, name } => {
+                write!(f, "files are in \"{dir}/\", but the unit is \"{name}\"")
+            }
+            Problem::Manifest(e) => e.fmt(f),
+            Problem::Hooks(problem) => write!(f, "payload: {problem}"),
+        }
+    }
+}
+
+impl Inspection {
+    pub fn new(archive: Archive) -> Result<Self, Error> {
+        let manifest = ManifestHeader::parse(&archive.manifest)?;
+        let hooks = HookTable::parse(&archive.payload)?;
+        Ok(Self {
+            archive,
+            manifest,
+            hooks,
+        })
+    }
+
+    /// Everything that would stop the unit loading, given the archive was
+    /// read from a file with `extension`.
+    pub fn check(&self, extension: Option<&str>) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        let expected = archive_extension(self.manifest.platform);
+        let actual = extension.unwrap_or_default();
+        if actual != expected {
+            problems.push(Problem::Extension {
+                actual: actual.to_owned(),
+                expected,
+            });
+        }
+
+        if self.archive.dir != self.manifest.name {
+            problems.push(Problem::Dir {
+                dir: self.archive.dir.clone(),
+                name: self.manifest.name.clone(),
+            });
+        }
+
+        if let Err(e) = check_manifest(&self.archive.manifest, &self.hooks.header) {
+            problems.push(Problem::Manifest(e.to_string()));
+        }
+
+        let text = hooks::bin_text(&self.archive.payload, &self.hooks);
+        problems.extend(
+            hooks::check(&self.hooks, text, Some(self.manifest.platform))
+                .into_iter()
+                .map(Problem::Hooks),
+        );
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest_json;
+    use logue_sdk::manifest::{Manifest, ParamDesc};
+    use logue_sdk::oscapi::API_VERSION;
+
+    const PARAMS: &[ParamDesc] = &[
+        ParamDesc::new("Depth", -100, 100, ParamUnit::Percent),
+        ParamDesc::new("Mode", 0, 3, ParamUnit::None),
+    ];
+
+    #[test]
+    fn parses_generated_manifest() {
+        let mut declared = Manifest::new(Platform::NutektDigital, Module::Osc, "osc", PARAMS);
+        declared.version = 0x01_02_03;
+
+        let header = ManifestHeader::parse(&manifest_json(&declared)).unwrap();
+        assert_eq!(
+            header,
+            ManifestHeader {
+                platform: Platform::NutektDigital,
+                module: Module::Osc,
+                api: API_VERSION,
+                dev_id: 0,
+                prg_id: 0,
+                version: 0x01_02_03,
+                name: "osc".into(),
+                params: vec![
+                    ManifestParam {
+                        name: "Depth".into(),
+                        min: -100,
+                        max: 100,
+                        unit: ParamUnit::Percent,
+                    },
+                    ManifestParam {
+                        name: "Mode".into(),
+                        min: 0,
+                        max: 3,
+                        unit: ParamUnit::None,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_sdk_manifest() {
+        let manifest = include_str!("../testdata/nutekt-digital-waves.json");
+        let header = ManifestHeader::parse(manifest).unwrap();
+        let param = |name: &str, max, unit| ManifestParam {
+            name: name.into(),
+            min: 0,
+            max,
+            unit,
+        };
+        assert_eq!(
+            header,
+            ManifestHeader {
+                platform: Platform::NutektDigital,
+                module: Module::Osc,
+                api: 0x01_01_00,
+                dev_id: 0,
+                prg_id: 0,
+                version: 0x01_00_00,
+                name: "waves".into(),
+                params: vec![
+                    param("Wave A", 45, ParamUnit::None),
+                    param("Wave B", 43, ParamUnit::None),
+                    param("Sub Wave", 15, ParamUnit::None),
+                    param("Sub Mix", 100, ParamUnit::Percent),
+                    param("Ring Mix", 100, ParamUnit::Percent),
+                    param("Bit Crush", 100, ParamUnit::Percent),
+                ],
+            }
+        );
+        assert_eq!(archive_extension(header.platform), "ntkdigunit");
+    }
+
+    fn archive_with(path: &Path, entries: &[&str]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for name in entries {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut zip, b"{}").unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_unit() {
+        let dir = std::env::temp_dir().join(format!("logue_inspect_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hostile.prlgunit");
+
+        for (manifest, payload) in [
+            ("../manifest.json", "../payload.bin"),
+            ("./manifest.json", "./payload.bin"),
+            ("/manifest.json", "/payload.bin"),
+            ("a\\..\\../manifest.json", "a\\..\\../payload.bin"),
+        ] {
+            archive_with(&path, &[manifest, payload]);
+            assert!(
+                matches!(Archive::open(&path), Err(Error::Archive(_))),
+                "{manifest}"
+            );
+        }
+
+        archive_with(&path, &["unit/manifest.json", "unit/payload.bin"]);
+        assert_eq!(Archive::open(&path).unwrap().dir, "unit");
+
+        for hostile in ["", ".", "..", "/tmp", "a/b", "..\\b"] {
+            let archive = Archive {
+                dir: hostile.into(),
+                manifest: String::new(),
+                payload: Vec::new(),
+            };
+            let e = archive.extract(&dir.join("out")).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{hostile:?}");
+        }
+        assert!(!dir.join("out").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_miscounted_params() {
+        let manifest = manifest_json(&Manifest::new(
+            Platform::Prologue,
+            Module::ModFx,
+            "fx",
+            PARAMS,
+        ))
+        .replace("\"num_param\" : 2", "\"num_param\" : 3");
+        assert!(matches!(
+            ManifestHeader::parse(&manifest),
+            Err(Error::Manifest(_))
+        ));
+    }
+}
@@ -1,12 +1,178 @@
 use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
 
-pub fn configure_osc_build() {
+use logue_sdk::manifest::Manifest;
+use logue_sdk::oscapi::API_VERSION;
+
+pub mod budget;
+#[cfg(feature = "emulator")]
+pub mod emu;
+pub mod hooks;
+pub mod inspect;
+#[cfg(feature = "emulator")]
+pub mod load;
+pub mod pack;
+pub mod payload;
+
+fn configure_build(script: &str) -> String {
     let ld = format!("{}/ld", env!("CARGO_MANIFEST_DIR"));
 
     // Set the main linker script
     println!("cargo:rustc-link-arg=-L{ld}");
-    println!("cargo:rustc-link-arg=-T{ld}/userosc.ld");
+    println!("cargo:rustc-link-arg=-T{ld}/{script}");
+
+    // Shown alongside the linker's error when the unit overflows its region,
+    // and the map, which is written even then, for `logue_size` to break down
+    let name = env::var("CARGO_PKG_NAME").unwrap();
+    let map = artifact_dir().join(format!("{name}.map"));
+    println!("cargo:rustc-link-arg=--print-memory-usage");
+    println!("cargo:rustc-link-arg=-Map={}", map.display());
+
+    ld
+}
+
+pub fn configure_osc_build() {
+    let ld = configure_build("userosc.ld");
 
     // Expose absolute symbols
     println!("cargo:rustc-link-arg={ld}/osc_api.syms");
 }
+
+pub fn configure_modfx_build() {
+    configure_build("usermodfx.ld");
+}
+
+pub fn configure_delfx_build() {
+    configure_build("userdelfx.ld");
+}
+
+pub fn configure_revfx_build() {
+    configure_build("userrevfx.ld");
+}
+
+/// drumlogue units are ARM Linux shared objects loaded by the firmware, so
+/// rather than a linker script they only need the package built as a
+/// `cdylib` for `armv7-unknown-linux-gnueabihf`.
+pub fn configure_drumlogue_build() {
+    let target = env::var("TARGET").unwrap_or_default();
+    if target != "armv7-unknown-linux-gnueabihf" {
+        println!("cargo:warning=drumlogue units should target armv7-unknown-linux-gnueabihf, not {target}");
+    }
+
+    let name = env::var("CARGO_PKG_NAME").unwrap();
+    println!("cargo:rustc-link-arg=-Wl,-soname,{name}.drmlgunit");
+}
+
+pub fn version_string(v: u32) -> String {
+    format!("{}.{}-{}", (v >> 16) & 0xFF, (v >> 8) & 0xFF, v & 0xFF)
+}
+
+/// The inverse of `version_string`.
+pub fn parse_version(s: &str) -> Option<u32> {
+    let (major, rest) = s.split_once('.')?;
+    let (minor, patch) = rest.split_once('-')?;
+    let [major, minor, patch] = [major, minor, patch].map(|n| n.parse::<u8>().ok());
+    Some(u32::from(major?) << 16 | u32::from(minor?) << 8 | u32::from(patch?))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Render `manifest` in the layout the librarian expects.
+pub fn manifest_json(manifest: &Manifest) -> String {
+    let mut params = String::new();
+    for (i, p) in manifest.params.iter().enumerate() {
+        let sep = if i + 1 == manifest.params.len() {
+            ""
+        } else {
+            ","
+        };
+        writeln!(
+            params,
+            "            [{}, {}, {}, {}]{sep}",
+            json_string(p.name),
+            p.min,
+            p.max,
+            json_string(p.unit.name()),
+        )
+        .unwrap();
+    }
+
+    format!(
+        r#"{{
+    "header" :
+    {{
+        "platform" : {platform},
+        "module" : {module},
+        "api" : "{api}",
+        "dev_id" : {dev_id},
+        "prg_id" : {prg_id},
+        "version" : "{version}",
+        "name" : {name},
+        "num_param" : {num_param},
+        "params" : [
+{params}          ]
+    }}
+}}
+"#,
+        platform = json_string(manifest.platform.name()),
+        module = json_string(manifest.module.name()),
+        api = version_string(API_VERSION),
+        dev_id = manifest.dev_id,
+        prg_id = manifest.prg_id,
+        version = version_string(manifest.version),
+        name = json_string(manifest.name),
+        num_param = manifest.params.len(),
+    )
+}
+
+/// Directory cargo puts the final artifacts of this build in, which is where
+/// packaging looks for the manifest.
+fn artifact_dir() -> PathBuf {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    // OUT_DIR is <artifact dir>/build/<package>-<hash>/out
+    out_dir.ancestors().nth(3).unwrap().to_path_buf()
+}
+
+/// Write `<package>.manifest.json` next to the unit's binaries.
+pub fn write_manifest(manifest: &Manifest) {
+    let name = env::var("CARGO_PKG_NAME").unwrap();
+    let path = artifact_dir().join(format!("{name}.manifest.json"));
+    fs::write(&path, manifest_json(manifest)).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logue_sdk::manifest::Module;
+    use logue_sdk::oscapi::Platform;
+    use serde_json::Value;
+
+    /// `platform/nutekt-digital/dummy-osc/manifest.json` from the logue SDK.
+    const SDK_MANIFEST: &str = include_str!("../testdata/nutekt-digital-dummy-osc.json");
+
+    #[test]
+    fn manifest_matches_sdk_template() {
+        let json: Value = serde_json::from_str(SDK_MANIFEST).unwrap();
+        let platform = json["header"]["platform"].as_str().unwrap();
+        assert_eq!(Platform::from_name(platform), Some(Platform::NutektDigital));
+        assert_eq!(Platform::NutektDigital.name(), platform);
+
+        let manifest = Manifest::new(Platform::NutektDigital, Module::Osc, "dummy", &[]);
+        let generated: Value = serde_json::from_str(&manifest_json(&manifest)).unwrap();
+        assert_eq!(generated, json);
+    }
+}
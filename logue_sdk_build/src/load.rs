@@ -0,0 +1,129 @@
+//! How much of the audio deadline an oscillator's `cycle` hook uses.
+//!
+//! The hook is run in the emulator over a range of notes and shape settings,
+//! since the work done per call often depends on them, and the estimated
+//! cycles of each call are compared with the time the block lasts at
+//! `SAMPLERATE` on the 144 MHz Cortex-M4.
+
+use std::fmt;
+
+use logue_sdk::oscapi::{OscParam, UserOscParam, MAX_FRAMES, SAMPLERATE};
+
+use crate::emu::{self, Emulator};
+
+pub const CPU_HZ: u64 = 144_000_000;
+
+/// Frames per `cycle` call measured by default, the most the firmware asks
+/// for at once.
+pub const DEFAULT_FRAMES: usize = MAX_FRAMES;
+
+/// The share of the deadline a unit may use by default, leaving headroom for
+/// the firmware's own processing and for error in the estimate.
+pub const DEFAULT_MAX_PERCENT: f64 = 75.0;
+
+const NOTES: [u16; 4] = [24, 48, 72, 96];
+const SHAPES: [u16; 3] = [0, 512, 1023];
+const CALLS_PER_SETTING: usize = 8;
+
+/// Cycles available to a call rendering `frames` frames.
+pub fn deadline(frames: usize) -> u64 {
+    frames as u64 * CPU_HZ / u64::from(SAMPLERATE)
+}
+
+#[derive(Debug)]
+pub struct Load {
+    pub frames: usize,
+    pub calls: usize,
+    pub worst: u64,
+    pub average: f64,
+}
+
+impl Load {
+    /// Run the `cycle` hook of an initialised unit, `frames` at a time.
+    pub fn measure(emu: &mut Emulator, frames: usize) -> Result<Self, emu::Error> {
+        let mut buf = vec![0; frames];
+        let mut calls = 0;
+        let mut worst = 0;
+        let mut total = 0;
+
+        for note in NOTES {
+            let params = UserOscParam {
+                pitch: note << 8,
+                ..Default::default()
+            };
+            emu.note_on(&params)?;
+            for shape in SHAPES {
+                emu.param(OscParam::ParamShape as u16, shape)?;
+                for shiftshape in SHAPES {
+                    emu.param(OscParam::ParamShiftShape as u16, shiftshape)?;
+                    for _ in 0..CALLS_PER_SETTING {
+                        emu.cycle(&params, &mut buf)?;
+                        calls += 1;
+                        worst = worst.max(emu.cycles);
+                        total += emu.cycles;
+                    }
+                }
+            }
+            emu.note_off(&params)?;
+        }
+
+        Ok(Self {
+            frames,
+            calls,
+            worst,
+            average: total as f64 / calls as f64,
+        })
+    }
+
+    pub fn deadline(&self) -> u64 {
+        deadline(self.frames)
+    }
+
+    /// The worst call's share of the deadline.
+    pub fn percent(&self) -> f64 {
+        self.worst as f64 * 100.0 / self.deadline() as f64
+    }
+
+    pub fn exceeds(&self, max_percent: f64) -> bool {
+        self.percent() > max_percent
+    }
+}
+
+impl fmt::Display for Load {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cycle of {} frames: worst {} cycles ({:.1}% of {}), average {:.0} over {} calls",
+            self.frames,
+            self.worst,
+            self.percent(),
+            self.deadline(),
+            self.average,
+            self.calls
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_at_48khz() {
+        assert_eq!(deadline(1), 3000);
+        assert_eq!(deadline(DEFAULT_FRAMES), 192_000);
+    }
+
+    #[test]
+    fn percent_of_worst_call() {
+        let load = Load {
+            frames: 64,
+            calls: 2,
+            worst: 96_000,
+            average: 50_000.0,
+        };
+        assert_eq!(load.percent(), 50.0);
+        assert!(load.exceeds(49.0));
+        assert!(!load.exceeds(50.0));
+    }
+}
@@ -0,0 +1,297 @@
+//! Unit archives: a zip of `<name>/manifest.json` and `<name>/payload.bin`,
+//! with an extension naming the platform it's for.
+
+use std::fmt;
+#[cfg(feature = "emulator")]
+use std::fs;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "emulator")]
+use logue_sdk::manifest::Module;
+use logue_sdk::oscapi::Platform;
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+#[cfg(feature = "emulator")]
+use crate::budget::Usage;
+#[cfg(feature = "emulator")]
+use crate::emu::{self, Emulator};
+#[cfg(feature = "emulator")]
+use crate::load::{Load, DEFAULT_FRAMES, DEFAULT_MAX_PERCENT};
+#[cfg(feature = "emulator")]
+use crate::payload::Payload;
+use crate::payload::{self, HooksHeader};
+use crate::version_string;
+
+pub fn archive_extension(platform: Platform) -> &'static str {
+    match platform {
+        Platform::Prologue => "prlgunit",
+        Platform::MinilogueXD => "mnlgxdunit",
+        Platform::NutektDigital => "ntkdigunit",
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Payload(payload::Error),
+    /// The zip doesn't hold a unit.
+    Archive(String),
+    Manifest(String),
+    /// The payload doesn't fit, with a report of what's using the space.
+    Budget(String),
+    /// The unit couldn't be run to measure its load.
+    #[cfg(feature = "emulator")]
+    Emulator(emu::Error),
+    /// The `cycle` hook takes too long.
+    #[cfg(feature = "emulator")]
+    Load(Load),
+    Io(io::Error),
+    /// The archive couldn't be written to the path.
+    Write(PathBuf, io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Payload(e) => write!(f, "payload: {e}"),
+            Error::Archive(e) => write!(f, "archive: {e}"),
+            Error::Manifest(e) => write!(f, "manifest: {e}"),
+            Error::Budget(report) => write!(f, "unit does not fit in memory\n{report}"),
+            #[cfg(feature = "emulator")]
+            Error::Emulator(e) => write!(f, "emulator: {e}"),
+            #[cfg(feature = "emulator")]
+            Error::Load(load) => write!(f, "unit is too slow\n{load}"),
+            Error::Io(e) => e.fmt(f),
+            Error::Write(path, e) => write!(f, "{}: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<payload::Error> for Error {
+    fn from(e: payload::Error) -> Self {
+        Error::Payload(e)
+    }
+}
+
+#[cfg(feature = "emulator")]
+impl From<emu::Error> for Error {
+    fn from(e: emu::Error) -> Self {
+        Error::Emulator(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::Io(e.into())
+    }
+}
+
+fn header_str<'a>(header: &'a Value, key: &str) -> Result<&'a str, Error> {
+    header[key]
+        .as_str()
+        .ok_or_else(|| Error::Manifest(format!("missing \"{key}\"")))
+}
+
+/// Check that `manifest` describes the unit whose hook table is `hooks`, and
+/// return the unit's name.
+pub fn check_manifest(manifest: &str, hooks: &HooksHeader) -> Result<String, Error> {
+    let json: Value = serde_json::from_str(manifest).map_err(|e| Error::Manifest(e.to_string()))?;
+    let header = &json["header"];
+
+    let expect = [
+        ("platform", hooks.platform.name().to_owned()),
+        ("module", hooks.module.name().to_owned()),
+        ("api", version_string(hooks.api)),
+    ];
+    for (key, expected) in expect {
+        let value = header_str(header, key)?;
+        if value != expected {
+            return Err(Error::Manifest(format!(
+                "\"{key}\" is \"{value}\", but the payload is for \"{expected}\""
+            )));
+        }
+    }
+
+    let name = header_str(header, "name")?;
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(Error::Manifest(format!("invalid name \"{name}\"")));
+    }
+    Ok(name.to_owned())
+}
+
+pub fn write_archive(path: &Path, name: &str, manifest: &str, payload: &[u8]) -> Result<(), Error> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+
+    zip.add_directory(format!("{name}/"), options)?;
+    zip.start_file(format!("{name}/manifest.json"), options)?;
+    zip.write_all(manifest.as_bytes())?;
+    zip.start_file(format!("{name}/payload.bin"), options)?;
+    zip.write_all(payload)?;
+    zip.finish()?;
+
+    Ok(())
+}
+
+/// Write the archive into `out_dir`, creating it if needed, and return its
+/// path.
+#[cfg(feature = "emulator")]
+fn write_unit(
+    out_dir: &Path,
+    name: &str,
+    manifest: &str,
+    payload: &Payload,
+) -> Result<PathBuf, Error> {
+    let path = out_dir.join(format!(
+        "{name}.{}",
+        archive_extension(payload.hooks.platform)
+    ));
+    let written = fs::create_dir_all(out_dir)
+        .map_err(Error::Io)
+        .and_then(|()| write_archive(&path, name, manifest, &payload.data));
+    match written {
+        Ok(()) => Ok(path),
+        Err(Error::Io(e)) => Err(Error::Write(path, e)),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(feature = "emulator")]
+pub struct Options {
+    /// Frames per `cycle` call when measuring an oscillator's load.
+    pub frames: usize,
+    /// The percentage of the deadline an oscillator's `cycle` hook may use,
+    /// or `None` not to measure it.
+    pub max_load: Option<f64>,
+}
+
+#[cfg(feature = "emulator")]
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            frames: DEFAULT_FRAMES,
+            max_load: Some(DEFAULT_MAX_PERCENT),
+        }
+    }
+}
+
+#[cfg(feature = "emulator")]
+pub struct Packed {
+    pub payload: Payload,
+    pub path: PathBuf,
+    /// The measured load, for oscillators.
+    pub load: Option<Load>,
+}
+
+/// Package a linked unit with its manifest into `out_dir`.
+#[cfg(feature = "emulator")]
+pub fn pack(
+    elf: &[u8],
+    manifest: &str,
+    out_dir: &Path,
+    options: &Options,
+) -> Result<Packed, Error> {
+    let payload = Payload::from_elf(elf)?;
+    let name = check_manifest(manifest, &payload.hooks)?;
+
+    let usage = Usage::new(&payload);
+    if usage.overflows() {
+        return Err(Error::Budget(usage.to_string()));
+    }
+
+    let mut load = None;
+    if let (Module::Osc, Some(max_load)) = (payload.hooks.module, options.max_load) {
+        let mut emu = Emulator::new(&payload.data)?;
+        emu.init()?;
+        let measured = Load::measure(&mut emu, options.frames)?;
+        if measured.exceeds(max_load) {
+            return Err(Error::Load(measured));
+        }
+        load = Some(measured);
+    }
+
+    let path = write_unit(out_dir, &name, manifest, &payload)?;
+
+    Ok(Packed {
+        payload,
+        path,
+        load,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest_json;
+    use logue_sdk::manifest::{Manifest, Module, ParamDesc, ParamUnit};
+    use logue_sdk::oscapi::API_VERSION;
+
+    const HOOKS: HooksHeader = HooksHeader {
+        module: Module::Osc,
+        api: API_VERSION,
+        platform: Platform::Prologue,
+    };
+
+    const PARAMS: &[ParamDesc] = &[ParamDesc::new("Depth", -100, 100, ParamUnit::Percent)];
+
+    #[test]
+    fn generated_manifest_matches_hooks() {
+        let manifest = manifest_json(&Manifest::new(
+            Platform::Prologue,
+            Module::Osc,
+            "test \"osc\"",
+            PARAMS,
+        ));
+        assert_eq!(check_manifest(&manifest, &HOOKS).unwrap(), "test \"osc\"");
+    }
+
+    #[test]
+    #[cfg(feature = "emulator")]
+    fn writes_into_new_dir() {
+        let payload = Payload {
+            base: 0x2000_0000,
+            data: vec![0; 16],
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            hooks: HOOKS,
+        };
+        let dir = std::env::temp_dir().join(format!("logue_pack_{}", std::process::id()));
+        let out_dir = dir.join("units");
+
+        let path = write_unit(&out_dir, "osc", "{}", &payload).unwrap();
+        assert_eq!(path, out_dir.join("osc.prlgunit"));
+        assert!(path.is_file());
+
+        // A file in the way of the directory is reported against the archive
+        let blocked = path.join("sub");
+        let e = write_unit(&blocked, "osc", "{}", &payload).unwrap_err();
+        assert!(matches!(&e, Error::Write(p, _) if *p == blocked.join("osc.prlgunit")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_platform() {
+        let manifest = manifest_json(&Manifest::new(
+            Platform::MinilogueXD,
+            Module::Osc,
+            "osc",
+            &[],
+        ));
+        assert!(matches!(
+            check_manifest(&manifest, &HOOKS),
+            Err(Error::Manifest(_))
+        ));
+    }
+}
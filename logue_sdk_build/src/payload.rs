@@ -0,0 +1,251 @@
+//! Extracting the image the firmware loads from a linked unit, the same bytes
+//! `objcopy -O binary` would produce.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::offset_of;
+
+use logue_sdk::manifest::Module;
+use logue_sdk::oscapi::{Platform, UserOscHookTable, API_VERSION};
+use logue_sdk::{delfx, modfx, oscapi, revfx};
+use object::elf::SHF_ALLOC;
+use object::read::elf::ElfFile32;
+use object::{Endianness, Object, ObjectSection, ObjectSymbol, SectionFlags, SectionKind};
+
+/// Magic, api and platform, followed by padding to the first hook. Every
+/// module's table shares the oscillator's header, and only the hooks after it
+/// differ.
+pub const HOOKS_HEADER_SIZE: usize = offset_of!(UserOscHookTable, func_entry);
+
+const MAGIC: usize = offset_of!(UserOscHookTable, magic);
+const API: usize = offset_of!(UserOscHookTable, api);
+const PLATFORM: usize = offset_of!(UserOscHookTable, platform);
+
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,
+    /// Whether the section's contents are part of the payload, rather than
+    /// only reserving memory like `.bss`.
+    pub loaded: bool,
+}
+
+impl Section {
+    pub fn end(&self) -> u32 {
+        self.address + self.size
+    }
+}
+
+/// A symbol with a size, such as a function or static, in an allocated
+/// section.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub section: String,
+    pub address: u32,
+    pub size: u32,
+}
+
+/// The fixed fields at the start of every v1 hook table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HooksHeader {
+    pub module: Module,
+    pub api: u32,
+    pub platform: Platform,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Elf(object::Error),
+    NotArm,
+    Empty,
+    MissingHooks,
+    HooksNotFirst { address: u32, base: u32 },
+    Truncated,
+    Magic([u8; 4]),
+    Api(u32),
+    Platform(u8),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Elf(e) => write!(f, "invalid ELF: {e}"),
+            Error::NotArm => write!(f, "not a 32-bit little-endian ARM ELF"),
+            Error::Empty => write!(f, "nothing to load"),
+            Error::MissingHooks => write!(f, "no .hooks section"),
+            Error::HooksNotFirst { address, base } => write!(
+                f,
+                ".hooks is at {address:#010x}, but the payload starts at {base:#010x}"
+            ),
+            Error::Truncated => write!(f, "payload is too short for a hook table"),
+            Error::Magic(magic) => write!(
+                f,
+                "unknown hook table magic {:?}",
+                String::from_utf8_lossy(magic)
+            ),
+            Error::Api(api) => write!(
+                f,
+                "api {} is not compatible with {}",
+                crate::version_string(*api),
+                crate::version_string(API_VERSION)
+            ),
+            Error::Platform(p) => write!(f, "unknown platform {p}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<object::Error> for Error {
+    fn from(e: object::Error) -> Self {
+        Error::Elf(e)
+    }
+}
+
+pub fn module_for_magic(magic: [u8; 4]) -> Option<Module> {
+    match magic {
+        oscapi::HOOKS_MAGIC => Some(Module::Osc),
+        modfx::HOOKS_MAGIC => Some(Module::ModFx),
+        delfx::HOOKS_MAGIC => Some(Module::DelFx),
+        revfx::HOOKS_MAGIC => Some(Module::RevFx),
+        _ => None,
+    }
+}
+
+/// Whether the firmware accepts a unit built against `api`: the same major
+/// version, and no newer minor version.
+pub fn api_is_compatible(api: u32) -> bool {
+    const MASK: u32 = 0xFF_FF_00;
+    api >> 16 == API_VERSION >> 16 && api & MASK <= API_VERSION & MASK
+}
+
+impl HooksHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let header = data.get(..HOOKS_HEADER_SIZE).ok_or(Error::Truncated)?;
+
+        let magic = [
+            header[MAGIC],
+            header[MAGIC + 1],
+            header[MAGIC + 2],
+            header[MAGIC + 3],
+        ];
+        let module = module_for_magic(magic).ok_or(Error::Magic(magic))?;
+
+        let api = u32::from_le_bytes([
+            header[API],
+            header[API + 1],
+            header[API + 2],
+            header[API + 3],
+        ]);
+        if !api_is_compatible(api) {
+            return Err(Error::Api(api));
+        }
+
+        let platform = header[PLATFORM]
+            .try_into()
+            .map_err(|_| Error::Platform(header[PLATFORM]))?;
+
+        Ok(Self {
+            module,
+            api,
+            platform,
+        })
+    }
+}
+
+pub struct Payload {
+    /// Load address of the first byte of `data`.
+    pub base: u32,
+    pub data: Vec<u8>,
+    /// Every allocated section, in address order.
+    pub sections: Vec<Section>,
+    /// Sized symbols in the allocated sections, largest first.
+    pub symbols: Vec<Symbol>,
+    pub hooks: HooksHeader,
+}
+
+impl Payload {
+    /// Lay out the allocated sections of a linked unit and check that it
+    /// starts with a valid hook table.
+    pub fn from_elf(data: &[u8]) -> Result<Self, Error> {
+        let elf = ElfFile32::<Endianness>::parse(data)?;
+        if elf.architecture() != object::Architecture::Arm || !elf.is_little_endian() {
+            return Err(Error::NotArm);
+        }
+
+        let mut sections = Vec::new();
+        let mut contents = Vec::new();
+        let mut section_names = HashMap::new();
+        for section in elf.sections() {
+            let SectionFlags::Elf { sh_flags } = section.flags() else {
+                continue;
+            };
+            if sh_flags & u64::from(SHF_ALLOC) == 0 || section.size() == 0 {
+                continue;
+            }
+
+            let loaded = section.kind() != SectionKind::UninitializedData;
+            let s = Section {
+                name: section.name()?.to_owned(),
+                address: section.address() as u32,
+                size: section.size() as u32,
+                loaded,
+            };
+            if loaded {
+                contents.push((s.address, section.data()?));
+            }
+            section_names.insert(section.index(), s.name.clone());
+            sections.push(s);
+        }
+        sections.sort_by_key(|s| s.address);
+
+        let mut symbols: Vec<Symbol> = elf
+            .symbols()
+            .filter(|sym| sym.size() != 0)
+            .filter_map(|sym| {
+                let section = section_names.get(&sym.section_index()?)?;
+                Some(Symbol {
+                    name: format!("{:#}", rustc_demangle::demangle(sym.name().ok()?)),
+                    section: section.clone(),
+                    address: sym.address() as u32,
+                    size: sym.size() as u32,
+                })
+            })
+            .collect();
+        symbols.sort_by(|a, b| b.size.cmp(&a.size).then(a.address.cmp(&b.address)));
+
+        let base = contents.iter().map(|(a, _)| *a).min().ok_or(Error::Empty)?;
+        let end = contents
+            .iter()
+            .map(|(a, d)| a + d.len() as u32)
+            .max()
+            .unwrap_or(base);
+
+        let mut image = vec![0; (end - base) as usize];
+        for (address, d) in contents {
+            let offset = (address - base) as usize;
+            image[offset..offset + d.len()].copy_from_slice(d);
+        }
+
+        let hooks = sections
+            .iter()
+            .find(|s| s.name == ".hooks")
+            .ok_or(Error::MissingHooks)?;
+        if hooks.address != base {
+            return Err(Error::HooksNotFirst {
+                address: hooks.address,
+                base,
+            });
+        }
+
+        Ok(Self {
+            base,
+            hooks: HooksHeader::parse(&image)?,
+            data: image,
+            sections,
+            symbols,
+        })
+    }
+}
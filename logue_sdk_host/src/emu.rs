@@ -0,0 +1,111 @@
+//! Running a unit's device build in the emulator, so what ships can be
+//! checked against the same scripts and references as the host build.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use logue_sdk::host::seed_white;
+use logue_sdk::oscapi::{OscParam, UserOscParam};
+use logue_sdk_build::emu::Emulator;
+use logue_sdk_build::inspect::ManifestHeader;
+
+use crate::render::Oscillator;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// A built oscillator payload running in the emulator. Any fault in the
+/// payload panics, since there's no sensible output to carry on with.
+pub struct EmulatedOsc {
+    emu: Emulator,
+    declared_params: usize,
+}
+
+impl EmulatedOsc {
+    /// Build `package`'s `<package>_logue` binary for the device the way
+    /// `build-logue.sh` does, then load and initialise it.
+    pub fn build(package: &str) -> Self {
+        let workspace = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let status = Command::new(cargo)
+            .current_dir(&workspace)
+            .args(["build", "--release", "--package", package])
+            .args(["--features", "logue_plugin", "--bin"])
+            .arg(format!("{package}_logue"))
+            .args(["--target", TARGET])
+            .status()
+            .unwrap_or_else(|e| panic!("cargo: {e}"));
+        assert!(status.success(), "building {package} for {TARGET} failed");
+
+        let target_dir =
+            env::var_os("CARGO_TARGET_DIR").map_or_else(|| workspace.join("target"), PathBuf::from);
+        let dir = target_dir.join(TARGET).join("release");
+
+        let path = dir.join(format!("{package}_logue"));
+        let elf = fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        let emu = Emulator::from_elf(&elf).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+
+        let path = dir.join(format!("{package}.manifest.json"));
+        let manifest = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|m| ManifestHeader::parse(&m).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+
+        let mut osc = Self {
+            emu,
+            declared_params: manifest.params.len(),
+        };
+        // The payload's noise comes from the host's generator, seeded as
+        // `init_osc` does
+        seed_white(0);
+        osc.emu.init().unwrap_or_else(|e| panic!("init: {e}"));
+        osc
+    }
+
+    pub fn emulator(&mut self) -> &mut Emulator {
+        &mut self.emu
+    }
+}
+
+impl Oscillator for EmulatedOsc {
+    fn declared_params(&self) -> usize {
+        self.declared_params
+    }
+
+    fn cycle(&mut self, params: &UserOscParam, buf: &mut [i32]) {
+        self.emu
+            .cycle(params, buf)
+            .unwrap_or_else(|e| panic!("cycle: {e}"));
+    }
+
+    fn note_on(&mut self, params: &UserOscParam) {
+        self.emu
+            .note_on(params)
+            .unwrap_or_else(|e| panic!("on: {e}"));
+    }
+
+    fn note_off(&mut self, params: &UserOscParam) {
+        self.emu
+            .note_off(params)
+            .unwrap_or_else(|e| panic!("off: {e}"));
+    }
+
+    fn mute(&mut self, params: &UserOscParam) {
+        self.emu
+            .mute(params)
+            .unwrap_or_else(|e| panic!("mute: {e}"));
+    }
+
+    fn value(&mut self, value: u16) {
+        self.emu
+            .value(value)
+            .unwrap_or_else(|e| panic!("value: {e}"));
+    }
+
+    fn param(&mut self, param: OscParam, value: u16) {
+        self.emu
+            .param(param as u16, value)
+            .unwrap_or_else(|e| panic!("param: {e}"));
+    }
+}
@@ -0,0 +1,368 @@
+//! Comparing renders against reference buffers checked in next to the tests.
+//!
+//! References are raw little-endian `f32` files, committed with the tests. A
+//! missing reference is a failure. Set `LOGUE_BLESS=1` to record new
+//! references, or overwrite them after an intended change in output. Renders
+//! of the device build are only ever compared, never recorded.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use logue_sdk::oscapi::UserOsc;
+
+use crate::emu::EmulatedOsc;
+use crate::script::Script;
+use crate::{init_osc, Renderer};
+
+const SPECTRUM_SIZE: usize = 1024;
+const SPECTRUM_FLOOR_DB: f32 = -100.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// Largest allowed difference of any one sample.
+    pub sample: f32,
+    /// Largest allowed RMS difference in dB between the magnitude spectra of
+    /// any 1024 frame window.
+    pub spectral_db: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            sample: 1.0e-6,
+            spectral_db: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Mismatch {
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+    Sample {
+        frame: usize,
+        expected: f32,
+        actual: f32,
+    },
+    Spectrum {
+        frame: usize,
+        distance_db: f32,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Length { expected, actual } => {
+                write!(f, "expected {expected} frames, rendered {actual}")
+            }
+            Mismatch::Sample {
+                frame,
+                expected,
+                actual,
+            } => write!(f, "frame {frame}: expected {expected}, rendered {actual}"),
+            Mismatch::Spectrum { frame, distance_db } => write!(
+                f,
+                "spectrum of window at frame {frame} is {distance_db:.3}dB from the reference"
+            ),
+        }
+    }
+}
+
+/// A directory of reference buffers, and how closely renders must match them.
+pub struct Golden {
+    dir: PathBuf,
+    tolerance: Tolerance,
+}
+
+impl Golden {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Render `script` through a freshly initialised `T` and check it against
+    /// the reference called `name`.
+    pub fn check_osc<T: UserOsc>(&self, name: &str, script: &Script) {
+        let samples = Renderer::new(init_osc::<T>()).render(script);
+        self.check(name, &samples);
+    }
+
+    /// Render `script` through `package`'s device build in the emulator and
+    /// check it against the reference called `name`, one recorded from the
+    /// host build. This builds `package` for the device, so tests calling it
+    /// are best left `#[ignore]`d.
+    pub fn check_emulated(&self, name: &str, package: &str, script: &Script) {
+        let path = self.path(name);
+        let reference = read_f32(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        let samples = Renderer::new(EmulatedOsc::build(package)).render(script);
+        if let Err(mismatch) = compare(&reference, &samples, &self.tolerance) {
+            panic!("{}: device build {mismatch}", path.display());
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.f32"))
+    }
+
+    /// Check `samples` against the reference called `name`, panicking with a
+    /// description of the first difference outside the tolerance.
+    pub fn check(&self, name: &str, samples: &[f32]) {
+        let path = self.path(name);
+        let bless = env::var_os("LOGUE_BLESS").is_some_and(|v| v != "0");
+
+        let reference = match read_f32(&path) {
+            Ok(_) if bless => None,
+            Ok(reference) => Some(reference),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && bless => None,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => panic!(
+                "{}: missing reference\n(rerun with LOGUE_BLESS=1 to record it)",
+                path.display()
+            ),
+            Err(e) => panic!("{}: {e}", path.display()),
+        };
+
+        match reference {
+            Some(reference) => {
+                if let Err(mismatch) = compare(&reference, samples, &self.tolerance) {
+                    panic!(
+                        "{}: {mismatch}\n(rerun with LOGUE_BLESS=1 if this change is intended)",
+                        path.display()
+                    );
+                }
+            }
+            None => {
+                write_f32(&path, samples)
+                    .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+                eprintln!("{}: recorded new reference", path.display());
+            }
+        }
+    }
+}
+
+pub fn read_f32(path: &Path) -> io::Result<Vec<f32>> {
+    let bytes = fs::read(path)?;
+    if bytes.len() % 4 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "length is not a whole number of samples",
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+pub fn write_f32(path: &Path, samples: &[f32]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let bytes: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+    fs::write(path, bytes)
+}
+
+pub fn compare(expected: &[f32], actual: &[f32], tolerance: &Tolerance) -> Result<(), Mismatch> {
+    if expected.len() != actual.len() {
+        return Err(Mismatch::Length {
+            expected: expected.len(),
+            actual: actual.len(),
+        });
+    }
+
+    for (frame, (&e, &a)) in expected.iter().zip(actual).enumerate() {
+        let error = (e - a).abs();
+        if error.is_nan() || error > tolerance.sample {
+            return Err(Mismatch::Sample {
+                frame,
+                expected: e,
+                actual: a,
+            });
+        }
+    }
+
+    let windows = expected
+        .chunks(SPECTRUM_SIZE)
+        .zip(actual.chunks(SPECTRUM_SIZE));
+    for (i, (e, a)) in windows.enumerate() {
+        let distance_db = spectral_distance_db(e, a);
+        if distance_db.is_nan() || distance_db > tolerance.spectral_db {
+            return Err(Mismatch::Spectrum {
+                frame: i * SPECTRUM_SIZE,
+                distance_db,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// RMS difference of the Hann windowed magnitude spectra of two windows, in
+/// dB, ignoring bins that are below the floor in both.
+pub fn spectral_distance_db(expected: &[f32], actual: &[f32]) -> f32 {
+    let e = magnitude_db(expected);
+    let a = magnitude_db(actual);
+
+    let (sum, count) = e
+        .iter()
+        .zip(&a)
+        .filter(|(e, a)| **e > SPECTRUM_FLOOR_DB || **a > SPECTRUM_FLOOR_DB)
+        .fold((0.0, 0), |(sum, count), (e, a)| {
+            (sum + (e - a) * (e - a), count + 1)
+        });
+
+    if count == 0 {
+        0.0
+    } else {
+        (sum / count as f32).sqrt()
+    }
+}
+
+fn magnitude_db(window: &[f32]) -> Vec<f32> {
+    let n = window.len();
+    let mut re = [0.0f32; SPECTRUM_SIZE];
+    let mut im = [0.0f32; SPECTRUM_SIZE];
+    for (i, &x) in window.iter().enumerate() {
+        let hann = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos();
+        re[i] = x * hann;
+    }
+
+    fft(&mut re, &mut im);
+
+    re.iter()
+        .zip(&im)
+        .take(SPECTRUM_SIZE / 2 + 1)
+        .map(|(re, im)| {
+            let mag = (re * re + im * im).sqrt() / SPECTRUM_SIZE as f32;
+            (20.0 * mag.log10()).max(SPECTRUM_FLOOR_DB)
+        })
+        .collect()
+}
+
+/// In place radix-2 FFT.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two());
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (wr, wi) = (cos as f32, sin as f32);
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * wr - im[b] * wi;
+                let ti = re[b] * wi + im[b] * wr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(bin: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * bin * i as f32 / SPECTRUM_SIZE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn fft_finds_sine() {
+        let mut re = [0.0; SPECTRUM_SIZE];
+        let mut im = [0.0; SPECTRUM_SIZE];
+        re.copy_from_slice(&sine(37.0, SPECTRUM_SIZE));
+        fft(&mut re, &mut im);
+
+        let peak = (0..SPECTRUM_SIZE / 2)
+            .max_by(|&a, &b| {
+                let ma = re[a] * re[a] + im[a] * im[a];
+                let mb = re[b] * re[b] + im[b] * im[b];
+                ma.total_cmp(&mb)
+            })
+            .unwrap();
+        assert_eq!(peak, 37);
+    }
+
+    #[test]
+    fn identical_buffers_match() {
+        let x = sine(10.0, 3000);
+        assert_eq!(compare(&x, &x, &Tolerance::default()), Ok(()));
+    }
+
+    #[test]
+    fn reports_first_sample_outside_tolerance() {
+        let expected = sine(10.0, 3000);
+        let mut actual = expected.clone();
+        actual[1234] += 0.01;
+        actual[2000] = f32::NAN;
+
+        assert!(matches!(
+            compare(&expected, &actual, &Tolerance::default()),
+            Err(Mismatch::Sample { frame: 1234, .. })
+        ));
+    }
+
+    #[test]
+    fn spectral_tolerance_catches_added_partial() {
+        let expected = sine(10.0, 2048);
+        let actual: Vec<f32> = expected
+            .iter()
+            .zip(sine(200.0, 2048))
+            .map(|(a, b)| a + 0.001 * b)
+            .collect();
+        let tolerance = Tolerance {
+            sample: 0.01,
+            spectral_db: 1.0,
+        };
+
+        assert!(matches!(
+            compare(&expected, &actual, &tolerance),
+            Err(Mismatch::Spectrum { frame: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn length_mismatch() {
+        assert_eq!(
+            compare(&[0.0; 4], &[0.0; 5], &Tolerance::default()),
+            Err(Mismatch::Length {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Running logue units on a development machine, outside of the synth and
+//! the browser.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use logue_sdk::host::seed_white;
+use logue_sdk::oscapi::{UserOsc, API_VERSION};
+use logue_sdk::poly::Poly;
+
+pub mod emu;
+pub mod golden;
+pub mod render;
+pub mod script;
+
+pub use render::{write_wav, Oscillator, Renderer};
+pub use script::Script;
+
+/// Initialise an oscillator as the firmware would, with a fixed noise seed so
+/// that repeated renders are identical.
+pub fn init_osc<T: UserOsc>() -> T {
+    seed_white(0);
+    T::init(T::PLATFORM as u32, API_VERSION)
+}
+
+/// The most voices `render_main` will run a unit with.
+pub const MAX_VOICES: usize = 8;
+
+fn usage(name: &str) -> ! {
+    eprintln!("usage: {name} [--no-gate] [--voices <1-{MAX_VOICES}>] <script> <output.wav>");
+    process::exit(2);
+}
+
+fn render<T: UserOsc>(gate: bool, script: &Script) -> Vec<f32> {
+    Renderer::new(init_osc::<T>())
+        .with_gate(gate)
+        .render(script)
+}
+
+/// Entry point for a `<unit>_render` binary: render a script through `T` and
+/// write the result as a 48kHz WAV file. With `--voices` the script plays
+/// chords on a `Poly` of that many `T`s.
+pub fn render_main<T: UserOsc>() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| "render".into());
+
+    let mut gate = true;
+    let mut voices = 1;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-gate" => gate = false,
+            "--voices" => {
+                voices = match args.next().map(|v| v.parse()) {
+                    Some(Ok(voices)) if (1..=MAX_VOICES).contains(&voices) => voices,
+                    _ => usage(&name),
+                }
+            }
+            _ if arg.starts_with('-') => usage(&name),
+            _ => paths.push(arg),
+        }
+    }
+    let [script_path, wav_path] = &paths[..] else {
+        usage(&name);
+    };
+
+    let script: Script = fs::read_to_string(script_path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| s.parse().map_err(|e: script::ParseError| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("{script_path}: {e}");
+            process::exit(1);
+        });
+
+    let samples = match voices {
+        1 => render::<T>(gate, &script),
+        2 => render::<Poly<T, 2>>(gate, &script),
+        3 => render::<Poly<T, 3>>(gate, &script),
+        4 => render::<Poly<T, 4>>(gate, &script),
+        5 => render::<Poly<T, 5>>(gate, &script),
+        6 => render::<Poly<T, 6>>(gate, &script),
+        7 => render::<Poly<T, 7>>(gate, &script),
+        _ => render::<Poly<T, MAX_VOICES>>(gate, &script),
+    };
+
+    if let Err(e) = write_wav(Path::new(wav_path), &samples) {
+        eprintln!("{wav_path}: {e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use logue_sdk::dsp::q31_to_f32;
+use logue_sdk::oscapi::{OscParam, UserOsc, UserOscParam, SAMPLERATE};
+
+use crate::script::{Event, Script};
+
+pub use logue_sdk::oscapi::MAX_FRAMES;
+
+/// What a `Renderer` drives: any `UserOsc`, or a built unit running in the
+/// emulator.
+pub trait Oscillator {
+    /// How many of `Param1` to `Param6` the unit declares.
+    fn declared_params(&self) -> usize;
+    fn cycle(&mut self, params: &UserOscParam, buf: &mut [i32]);
+    fn note_on(&mut self, params: &UserOscParam);
+    fn note_off(&mut self, params: &UserOscParam);
+    fn mute(&mut self, params: &UserOscParam);
+    fn value(&mut self, value: u16);
+    fn param(&mut self, param: OscParam, value: u16);
+}
+
+impl<T: UserOsc> Oscillator for T {
+    fn declared_params(&self) -> usize {
+        T::PARAMS.len()
+    }
+
+    fn cycle(&mut self, params: &UserOscParam, buf: &mut [i32]) {
+        UserOsc::cycle(self, params, buf)
+    }
+
+    fn note_on(&mut self, params: &UserOscParam) {
+        UserOsc::note_on(self, params)
+    }
+
+    fn note_off(&mut self, params: &UserOscParam) {
+        UserOsc::note_off(self, params)
+    }
+
+    fn mute(&mut self, params: &UserOscParam) {
+        UserOsc::mute(self, params)
+    }
+
+    fn value(&mut self, value: u16) {
+        UserOsc::value(self, value)
+    }
+
+    fn param(&mut self, param: OscParam, value: u16) {
+        UserOsc::param(self, param, value)
+    }
+}
+
+/// Drives an oscillator the way the firmware would, without the rest of the
+/// voice. Note velocity isn't part of the oscillator API, so it's applied as
+/// output gain, and by default the output is silenced while no note is held.
+pub struct Renderer<T: Oscillator> {
+    osc: T,
+    params: UserOscParam,
+    gain: f32,
+    gate: bool,
+    /// Pitches of the notes held, in the order they started.
+    held: Vec<u16>,
+}
+
+impl<T: Oscillator> Renderer<T> {
+    pub fn new(osc: T) -> Self {
+        Self {
+            osc,
+            params: UserOscParam {
+                pitch: 60 << 8,
+                ..Default::default()
+            },
+            gain: 0.0,
+            gate: true,
+            held: Vec::new(),
+        }
+    }
+
+    /// When the gate is off the oscillator is heard all the time, at the
+    /// velocity of the last note.
+    pub fn with_gate(mut self, gate: bool) -> Self {
+        self.gate = gate;
+        if !gate {
+            self.gain = 1.0;
+        }
+        self
+    }
+
+    pub fn osc(&mut self) -> &mut T {
+        &mut self.osc
+    }
+
+    pub fn params(&mut self) -> &mut UserOscParam {
+        &mut self.params
+    }
+
+    pub fn apply(&mut self, event: &Event) {
+        match *event {
+            Event::NoteOn { pitch, velocity } => {
+                self.params.pitch = pitch;
+                self.gain = velocity as f32 / 127.0;
+                self.held.retain(|&p| p >> 8 != pitch >> 8);
+                self.held.push(pitch);
+                self.osc.note_on(&self.params);
+            }
+            Event::NoteOff { pitch: Some(pitch) } => {
+                self.held.retain(|&p| p >> 8 != pitch >> 8);
+                self.note_off(pitch);
+            }
+            Event::NoteOff { pitch: None } => {
+                let held = std::mem::take(&mut self.held);
+                if held.is_empty() {
+                    self.note_off(self.params.pitch);
+                }
+                for pitch in held {
+                    self.note_off(pitch);
+                }
+            }
+            Event::Mute => self.osc.mute(&self.params),
+            Event::Param { idx, value } => {
+                // The firmware only sends the numbered parameters the unit
+                // declares.
+                if idx < OscParam::ParamShape as u16 && idx as usize >= self.osc.declared_params() {
+                    return;
+                }
+                if let Ok(param) = idx.try_into() {
+                    self.osc.param(param, value);
+                }
+            }
+            Event::Value(value) => self.osc.value(value),
+            Event::ShapeLfo(lfo) => self.params.shape_lfo = lfo,
+        }
+    }
+
+    fn note_off(&mut self, pitch: u16) {
+        if self.gate && self.held.is_empty() {
+            self.gain = 0.0;
+        }
+        self.params.pitch = pitch;
+        self.osc.note_off(&self.params);
+        // Like a mono synth with last note priority, the pitch goes back to
+        // the last note still held
+        if let Some(&last) = self.held.last() {
+            self.params.pitch = last;
+        }
+    }
+
+    /// Run the oscillator for `out.len()` frames, in firmware sized blocks.
+    pub fn cycle(&mut self, out: &mut [f32]) {
+        let mut q31 = [0i32; MAX_FRAMES];
+        for chunk in out.chunks_mut(MAX_FRAMES) {
+            let buf = &mut q31[..chunk.len()];
+            self.osc.cycle(&self.params, buf);
+            for (y, x) in chunk.iter_mut().zip(buf.iter()) {
+                *y = q31_to_f32(*x) * self.gain;
+            }
+        }
+    }
+
+    /// Render a whole script, applying each event at its exact frame.
+    pub fn render(&mut self, script: &Script) -> Vec<f32> {
+        let mut out = vec![0.0; script.frames()];
+        let mut frame = 0;
+        let mut events = script.events().iter().peekable();
+
+        loop {
+            while let Some(e) = events.next_if(|e| e.frame <= frame) {
+                self.apply(&e.event);
+            }
+
+            if frame >= out.len() {
+                break;
+            }
+
+            let next = events.peek().map_or(out.len(), |e| e.frame.min(out.len()));
+            self.cycle(&mut out[frame..next]);
+            frame = next;
+        }
+
+        out
+    }
+}
+
+pub fn write_wav(path: &Path, samples: &[f32]) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLERATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &x in samples {
+        writer.write_sample(x)?;
+    }
+    writer.finalize()
+}
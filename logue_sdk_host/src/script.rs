@@ -0,0 +1,269 @@
+//! A line based description of what to play through an oscillator.
+//!
+//! Each line is a time in seconds followed by a command:
+//!
+//! ```text
+//! # comments run to the end of the line
+//! 0.0 param shape 512
+//! 0.0 on 60 100       # note, optional velocity
+//! 0.5 param 1 50
+//! 1.0 on 64.5         # fractional notes set the fine pitch byte
+//! 2.0 off
+//! 2.5 end
+//! ```
+//!
+//! Notes can overlap, for chords on a `logue_sdk::poly::Poly`, in which case
+//! `off <note>` releases just the one note and `off` all of them.
+//!
+//! Commands are `on <note> [velocity]`, `off [note]`, `mute`,
+//! `param <1-6|shape|shiftshape> <value>`, `value <value>`, `lfo <shape_lfo>`
+//! and `end`. Times must not go backwards, or past an hour. Without `end` the
+//! render stops at the last event.
+
+use std::fmt;
+use std::str::FromStr;
+
+use logue_sdk::oscapi::{OscParam, SAMPLERATE};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    NoteOn {
+        pitch: u16,
+        velocity: u8,
+    },
+    /// Release one note, or every note held.
+    NoteOff {
+        pitch: Option<u16>,
+    },
+    Mute,
+    Param {
+        idx: u16,
+        value: u16,
+    },
+    Value(u16),
+    ShapeLfo(i32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedEvent {
+    pub frame: usize,
+    pub event: Event,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script {
+    events: Vec<TimedEvent>,
+    frames: usize,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an event. Events must be pushed in time order.
+    pub fn push(&mut self, frame: usize, event: Event) -> &mut Self {
+        assert!(
+            frame >= self.frames,
+            "event at frame {frame} is before the end of the script"
+        );
+        self.events.push(TimedEvent { frame, event });
+        self.frames = frame;
+        self
+    }
+
+    /// Extend the script to `frames` long without adding an event.
+    pub fn end(&mut self, frames: usize) -> &mut Self {
+        assert!(frames >= self.frames, "script end is before its last event");
+        self.frames = frames;
+        self
+    }
+
+    pub fn events(&self) -> &[TimedEvent] {
+        &self.events
+    }
+
+    /// Total length of the script, in frames.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+}
+
+/// The latest time a script can reach, in seconds.
+pub const MAX_SECONDS: f64 = 3600.0;
+
+pub fn seconds_to_frames(seconds: f64) -> usize {
+    (seconds * SAMPLERATE as f64).round() as usize
+}
+
+fn parse_pitch(s: &str) -> Result<u16, String> {
+    let note: f32 = s.parse().map_err(|_| format!("invalid note: {s}"))?;
+    if !(0.0..128.0).contains(&note) {
+        return Err(format!("note out of range: {s}"));
+    }
+    let fine = ((note - note.trunc()) * 256.0) as u16;
+    Ok((note as u16) << 8 | fine.min(0xFF))
+}
+
+fn parse_param_idx(s: &str) -> Result<u16, String> {
+    let param = match s {
+        "1" => OscParam::Param1,
+        "2" => OscParam::Param2,
+        "3" => OscParam::Param3,
+        "4" => OscParam::Param4,
+        "5" => OscParam::Param5,
+        "6" => OscParam::Param6,
+        "shape" => OscParam::ParamShape,
+        "shiftshape" => OscParam::ParamShiftShape,
+        _ => return Err(format!("unknown parameter: {s}")),
+    };
+    Ok(param as u16)
+}
+
+fn parse_arg<T: FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("missing {what}"))?;
+    arg.parse().map_err(|_| format!("invalid {what}: {arg}"))
+}
+
+fn parse_line(script: &mut Script, line: &str) -> Result<(), String> {
+    let mut words = line.split_whitespace();
+    let Some(time) = words.next() else {
+        return Ok(());
+    };
+
+    let seconds: f64 = time.parse().map_err(|_| format!("invalid time: {time}"))?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("invalid time: {time}"));
+    }
+    if seconds > MAX_SECONDS {
+        return Err(format!("time is past {MAX_SECONDS} seconds: {time}"));
+    }
+    let frame = seconds_to_frames(seconds);
+    if frame < script.frames() {
+        return Err(format!("time goes backwards: {time}"));
+    }
+
+    let command = words.next().ok_or("missing command")?;
+    let event = match command {
+        "on" => {
+            let pitch = parse_pitch(words.next().ok_or("missing note")?)?;
+            let velocity = match words.next() {
+                Some(v) => parse_arg::<u8>(Some(v), "velocity")?.min(127),
+                None => 127,
+            };
+            Event::NoteOn { pitch, velocity }
+        }
+        "off" => Event::NoteOff {
+            pitch: words.next().map(parse_pitch).transpose()?,
+        },
+        "mute" => Event::Mute,
+        "param" => {
+            let idx = parse_param_idx(words.next().ok_or("missing parameter")?)?;
+            let value = parse_arg(words.next(), "parameter value")?;
+            Event::Param { idx, value }
+        }
+        "value" => Event::Value(parse_arg(words.next(), "value")?),
+        "lfo" => Event::ShapeLfo(parse_arg(words.next(), "lfo value")?),
+        "end" => {
+            script.end(frame);
+            return match words.next() {
+                Some(extra) => Err(format!("unexpected argument: {extra}")),
+                None => Ok(()),
+            };
+        }
+        _ => return Err(format!("unknown command: {command}")),
+    };
+
+    if let Some(extra) = words.next() {
+        return Err(format!("unexpected argument: {extra}"));
+    }
+
+    script.push(frame, event);
+    Ok(())
+}
+
+impl FromStr for Script {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut script = Script::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            parse_line(&mut script, line).map_err(|message| ParseError {
+                line: i + 1,
+                message,
+            })?;
+        }
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(script: &str) -> ParseError {
+        script.parse::<Script>().unwrap_err()
+    }
+
+    #[test]
+    fn parses_events_at_their_frames() {
+        let script: Script = "0 on 60.5 100\n0.5 off # comment\n1 end".parse().unwrap();
+        let events: Vec<_> = script.events().iter().map(|e| (e.frame, e.event)).collect();
+        assert_eq!(
+            events,
+            [
+                (
+                    0,
+                    Event::NoteOn {
+                        pitch: 60 << 8 | 128,
+                        velocity: 100
+                    }
+                ),
+                (24_000, Event::NoteOff { pitch: None }),
+            ]
+        );
+        assert_eq!(script.frames(), 48_000);
+    }
+
+    #[test]
+    fn rejects_bad_times() {
+        let e = error("1 on 60\n0.5 off");
+        assert_eq!(
+            (e.line, e.message.as_str()),
+            (2, "time goes backwards: 0.5")
+        );
+        assert_eq!(error("inf end").message, "invalid time: inf");
+        assert_eq!(error("0 on 60\ninf end").message, "invalid time: inf");
+        assert_eq!(error("NaN end").message, "invalid time: NaN");
+        assert_eq!(error("-1 end").message, "invalid time: -1");
+        assert_eq!(
+            error("1e300 end").message,
+            "time is past 3600 seconds: 1e300"
+        );
+        assert!("3600 end".parse::<Script>().is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_notes_and_params() {
+        assert_eq!(error("0 on 128").message, "note out of range: 128");
+        assert_eq!(error("0 on c4").message, "invalid note: c4");
+        assert_eq!(error("0 param 7 10").message, "unknown parameter: 7");
+        assert_eq!(error("0 param shape").message, "missing parameter value");
+        assert_eq!(error("0 param 1 x").message, "invalid parameter value: x");
+    }
+}
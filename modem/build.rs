@@ -1,4 +1,8 @@
+include!("src/manifest.rs");
+
 pub fn main() {
     #[cfg(feature = "logue_plugin")]
     logue_sdk_build::configure_osc_build();
+
+    logue_sdk_build::write_manifest(&MANIFEST);
 }
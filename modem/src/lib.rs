@@ -6,11 +6,16 @@ use core::mem;
 use core::slice;
 
 use logue_sdk::dsp::f32_to_q31;
+use logue_sdk::manifest::ParamDesc;
 use logue_sdk::oscapi::{
     cubicsat_lut_f, osc_wave_scanf, schetzen_lut_f, wavesA, Platform, UserOsc, UserOscParam,
     SAMPLERATE, SAMPLERATE_RECIPF,
 };
 
+mod manifest;
+
+use manifest::MANIFEST;
+
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 struct Phi(f32);
@@ -160,18 +165,11 @@ impl<T: ModemParams> Iterator for Modem<T> {
 }
 
 impl<T: ModemParams> UserOsc for Modem<T> {
-    const PLATFORM: Platform = Platform::MinilogueXD;
+    const PLATFORM: Platform = MANIFEST.platform;
+    const PARAMS: &'static [ParamDesc] = MANIFEST.params;
 
     fn init(_platform: u32, _api: u32) -> Self {
-        let mut modem = Modem::new();
-
-        if cfg!(feature = "wasm_module") {
-            // The WASM interface doesn't support note data in any form.
-            // Default to sending something so we can test.
-            modem.send(b"Hello, world!");
-        }
-
-        modem
+        Modem::new()
     }
 
     fn cycle(&mut self, _params: &UserOscParam, buf: &mut [i32]) {
@@ -0,0 +1,6 @@
+// Shared with build.rs, which writes manifest.json from it.
+
+use logue_sdk::manifest::{Manifest, Module};
+use logue_sdk::oscapi::Platform;
+
+pub const MANIFEST: Manifest = Manifest::new(Platform::MinilogueXD, Module::Osc, "modem", &[]);
@@ -0,0 +1,65 @@
+use logue_sdk::oscapi::SAMPLERATE_RECIPF;
+use logue_sdk_host::golden::{Golden, Tolerance};
+use logue_sdk_host::Script;
+use modem::{Bell103, Modem, ModemParams, SampleIter};
+
+fn golden() -> Golden {
+    Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden")).tolerance(Tolerance {
+        sample: 1.0e-5,
+        spectral_db: 0.1,
+    })
+}
+
+#[test]
+fn sample_iter_timing() {
+    let w0: Vec<f32> = SampleIter::<Bell103>::new(b"Hi").collect();
+
+    // Start bit, 8 data bits and a stop bit for each byte
+    assert_eq!(w0.len(), 2 * 10 * Bell103::SAMPLES_PER_BIT);
+
+    // The frequencies don't depend on any tables, so must match exactly
+    golden()
+        .tolerance(Tolerance {
+            sample: 0.0,
+            spectral_db: 0.0,
+        })
+        .check("bell103_hi_w0", &w0);
+}
+
+#[test]
+fn carrier_precedes_data() {
+    let mut modem = Modem::<Bell103>::new();
+    modem.send(b"\x00");
+
+    let w0: Vec<f32> = modem.collect();
+    let carrier = 48_000 / 20;
+    assert!(w0[..carrier].iter().all(|&w| w == Bell103::ONE_W0));
+
+    // Start bit and all zero data bits
+    let zeros = &w0[carrier..carrier + 9 * Bell103::SAMPLES_PER_BIT];
+    assert!(zeros.iter().all(|&w| w == Bell103::ZERO_W0));
+    assert_eq!(Bell103::ZERO_W0, 1070.0 * SAMPLERATE_RECIPF);
+}
+
+fn transmit_script() -> Script {
+    "
+    0.0 on 60
+    0.3 on 61
+    0.6 on 40
+    0.7 end
+    "
+    .parse()
+    .unwrap()
+}
+
+#[test]
+fn transmit() {
+    golden().check_osc::<Modem<Bell103>>("transmit", &transmit_script());
+}
+
+/// The device build, run in the emulator, against the same reference.
+#[test]
+#[ignore = "builds the device target"]
+fn transmit_on_device() {
+    golden().check_emulated("transmit", "modem", &transmit_script());
+}
@@ -1,4 +1,10 @@
+include!("src/manifest.rs");
+
 pub fn main() {
     #[cfg(feature = "logue_plugin")]
     logue_sdk_build::configure_osc_build();
+    #[cfg(feature = "drumlogue_unit")]
+    logue_sdk_build::configure_drumlogue_build();
+
+    logue_sdk_build::write_manifest(&MANIFEST);
 }
@@ -0,0 +1,3 @@
+fn main() {
+    logue_sdk_host::render_main::<noise::Noise>();
+}
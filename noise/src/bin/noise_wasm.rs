@@ -1,3 +1,3 @@
 #![no_main]
 
-logue_sdk::user_osc_wasm_functions!(noise::Noise);
+logue_sdk::user_osc_wasm_functions!(noise::Noise, voices = 4);
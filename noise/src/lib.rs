@@ -1,11 +1,23 @@
 #![no_std]
 
+// drumlogue units are Linux shared objects, where std provides the panic
+// handler
+#[cfg(feature = "drumlogue_unit")]
+extern crate std;
+
 use logue_sdk::dsp::{f32_to_q31, param_val_to_f32, si_roundf};
+use logue_sdk::manifest::ParamDesc;
 use logue_sdk::oscapi::{
     osc_bitresf, osc_w0f_for_note, osc_wave_scanf, pick1, wavesA, OscParam, Platform, UserOsc,
     UserOscParam,
 };
 
+mod manifest;
+#[cfg(feature = "drumlogue_unit")]
+pub mod unit;
+
+use manifest::MANIFEST;
+
 #[derive(Clone, Copy)]
 #[repr(transparent)]
 struct W0(f32);
@@ -86,7 +98,8 @@ pub struct Noise {
 }
 
 impl UserOsc for Noise {
-    const PLATFORM: Platform = Platform::MinilogueXD;
+    const PLATFORM: Platform = MANIFEST.platform;
+    const PARAMS: &'static [ParamDesc] = MANIFEST.params;
 
     fn init(_platform: u32, _api: u32) -> Self {
         Noise::default()
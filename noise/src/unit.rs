@@ -0,0 +1,119 @@
+//! The oscillator as a drumlogue synth unit, gated by notes and played on
+//! both outputs.
+
+use logue_sdk::dsp::q31_to_f32;
+use logue_sdk::oscapi::{OscParam, UserOsc, UserOscParam, MAX_FRAMES};
+use logue_sdk::unit::{
+    check_geometry, render_frames, ParamType, RenderFrames, Unit, UnitError, UnitHeader,
+    UnitModule, UnitParam, UnitRuntimeDesc,
+};
+
+use crate::Noise;
+
+const PARAMS: [(OscParam, UnitParam); 2] = [
+    (
+        OscParam::ParamShape,
+        UnitParam::new("Shape", 0, 1023, 0, 0, ParamType::None),
+    ),
+    (
+        OscParam::ParamShiftShape,
+        UnitParam::new("Crush", 0, 1023, 0, 0, ParamType::None),
+    ),
+];
+
+pub struct NoiseUnit {
+    osc: Noise,
+    params: UserOscParam,
+    values: [i32; PARAMS.len()],
+    note: Option<u8>,
+}
+
+impl NoiseUnit {
+    fn trigger(&mut self, note: u8) {
+        self.params.pitch = u16::from(note) << 8;
+        self.note = Some(note);
+    }
+}
+
+impl Unit for NoiseUnit {
+    const HEADER: UnitHeader = UnitHeader::new(
+        UnitModule::Synth,
+        0,
+        0,
+        0x01_00_00,
+        "noise",
+        &[PARAMS[0].1, PARAMS[1].1],
+    );
+
+    fn init(desc: &UnitRuntimeDesc) -> Result<Self, UnitError> {
+        check_geometry::<0, 2>(desc)?;
+        Ok(Self {
+            osc: Noise::init(0, 0),
+            params: UserOscParam {
+                pitch: 60 << 8,
+                ..Default::default()
+            },
+            values: [0; PARAMS.len()],
+            note: None,
+        })
+    }
+
+    fn render(&mut self, input: &[f32], output: &mut [f32], frames: usize) {
+        render_frames(self, input, output, frames);
+    }
+
+    fn get_param_value(&self, id: u8) -> i32 {
+        self.values.get(usize::from(id)).copied().unwrap_or(0)
+    }
+
+    fn set_param_value(&mut self, id: u8, value: i32) {
+        let Some((param, desc)) = PARAMS.get(usize::from(id)) else {
+            return;
+        };
+        let value = value.clamp(i32::from(desc.min), i32::from(desc.max));
+        self.values[usize::from(id)] = value;
+        self.osc.param(*param, value as u16);
+    }
+
+    fn note_on(&mut self, note: u8, _velocity: u8) {
+        self.trigger(note);
+    }
+
+    fn note_off(&mut self, note: u8) {
+        if self.note == Some(note) {
+            self.note = None;
+        }
+    }
+
+    fn gate_on(&mut self, _velocity: u8) {
+        self.trigger((self.params.pitch >> 8) as u8);
+    }
+
+    fn gate_off(&mut self) {
+        self.note = None;
+    }
+
+    fn all_note_off(&mut self) {
+        self.note = None;
+    }
+}
+
+impl RenderFrames<0, 2> for NoiseUnit {
+    fn render_frames(&mut self, _input: &[[f32; 0]], output: &mut [[f32; 2]]) {
+        // `cycle` is never asked for more than the firmware would
+        let mut buf = [0; MAX_FRAMES];
+        for output in output.chunks_mut(buf.len()) {
+            let buf = &mut buf[..output.len()];
+            if self.note.is_some() {
+                self.osc.cycle(&self.params, buf);
+            } else {
+                buf.fill(0);
+            }
+            for (frame, &x) in output.iter_mut().zip(buf.iter()) {
+                *frame = [q31_to_f32(x); 2];
+            }
+        }
+    }
+}
+
+logue_sdk::unit_hooks!(NoiseUnit);
@@ -0,0 +1,47 @@
+//! Drives the drumlogue unit through its hooks, the way the firmware would.
+#![cfg(feature = "drumlogue_unit")]
+
+use core::ptr;
+
+use logue_sdk::unit::{UnitRuntimeDesc, UnitRuntimeHooks, API_VERSION, TARGET_DRUMLOGUE};
+use noise::unit::{
+    unit_get_param_value, unit_header, unit_init, unit_note_off, unit_note_on, unit_render,
+    unit_set_param_value,
+};
+
+#[test]
+fn plays_notes_on_both_outputs() {
+    let desc = UnitRuntimeDesc {
+        target: { unit_header.target },
+        api: API_VERSION,
+        samplerate: 48_000,
+        frames_per_buffer: 64,
+        input_channels: 0,
+        output_channels: 2,
+        hooks: UnitRuntimeHooks {
+            runtime_context: ptr::null_mut(),
+            get_num_sample_banks: None,
+            get_num_samples_for_bank: None,
+            get_sample: None,
+        },
+    };
+    assert_eq!({ unit_header.target } & TARGET_DRUMLOGUE, TARGET_DRUMLOGUE);
+    assert_eq!(unit_init(&desc), 0);
+
+    let mut out = [1.0; 2 * 100];
+    unit_render(ptr::null(), out.as_mut_ptr(), 100);
+    assert!(out.iter().all(|&x| x == 0.0));
+
+    unit_note_on(60, 100);
+    unit_render(ptr::null(), out.as_mut_ptr(), 100);
+    assert!(out.iter().any(|&x| x != 0.0));
+    assert!(out.chunks(2).all(|frame| frame[0] == frame[1]));
+
+    unit_note_off(60);
+    unit_render(ptr::null(), out.as_mut_ptr(), 100);
+    assert!(out.iter().all(|&x| x == 0.0));
+
+    unit_set_param_value(1, 2000);
+    assert_eq!(unit_get_param_value(1), 1023);
+    assert_eq!(unit_get_param_value(7), 0);
+}
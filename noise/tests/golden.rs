@@ -0,0 +1,70 @@
+use logue_sdk_host::golden::{Golden, Tolerance};
+use logue_sdk_host::Script;
+use noise::Noise;
+
+fn golden() -> Golden {
+    Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden")).tolerance(Tolerance {
+        sample: 1.0e-5,
+        spectral_db: 0.1,
+    })
+}
+
+fn script(s: &str) -> Script {
+    s.parse().unwrap()
+}
+
+#[test]
+fn wave_scan() {
+    golden().check_osc::<Noise>(
+        "wave_scan",
+        &script(
+            "
+            0.0 on 60
+            0.1 param shape 128
+            0.2 param shape 256
+            0.3 param shape 512
+            0.4 param shape 768
+            0.5 param shape 1023
+            0.6 end
+            ",
+        ),
+    );
+}
+
+const PITCH: &str = "
+    0.0 on 24
+    0.1 on 60.5
+    0.2 on 96 64
+    0.3 on 127
+    0.4 end
+    ";
+
+#[test]
+fn pitch() {
+    golden().check_osc::<Noise>("pitch", &script(PITCH));
+}
+
+/// The device build, run in the emulator, against the same reference.
+#[test]
+#[ignore = "builds the device target"]
+fn pitch_on_device() {
+    golden().check_emulated("pitch", "noise", &script(PITCH));
+}
+
+#[test]
+fn bitcrush() {
+    golden().check_osc::<Noise>(
+        "bitcrush",
+        &script(
+            "
+            0.0 on 48
+            0.0 param shape 300
+            0.1 param shiftshape 1
+            0.2 param shiftshape 100
+            0.3 param shiftshape 500
+            0.4 param shiftshape 1023
+            0.5 end
+            ",
+        ),
+    );
+}
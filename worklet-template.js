@@ -0,0 +1,127 @@
+// Runs @MODULE_NAME@.wasm in real time, calling `cycle` once per render
+// quantum. The page passes the module's bytes in processorOptions and sends
+// notes and knob changes through the port.
+
+const _osc_white = () => {
+  return Math.fround(Math.random() * 2 - 1);
+};
+
+class OscProcessor extends AudioWorkletProcessor {
+  constructor(options) {
+    super();
+    const module = new WebAssembly.Module(options.processorOptions.wasm);
+    this.osc = new WebAssembly.Instance(module, { env: { _osc_white } }).exports;
+    this.osc.init(0, 0);
+
+    // Render quanta are 128 frames, but grow the buffer if they ever aren't
+    this.allocate(128);
+
+    // Notes held, the last of which a single voice plays, and the pitch bend
+    // in semitones
+    this.voices = this.osc.voices();
+    this.held = [];
+    this.bend = 0;
+
+    // Velocity isn't part of the oscillator API, so the last note's is applied
+    // as gain, and the output is silent between notes
+    this.gain = 0;
+
+    this.port.onmessage = (event) => this.message(event.data);
+  }
+
+  allocate(frames) {
+    if (this.buf) {
+      this.osc.free_sample_buffer(this.buf, this.frames);
+    }
+    this.frames = frames;
+    this.buf = this.osc.allocate_sample_buffer(frames);
+    this.samples = new Float32Array(this.osc.memory.buffer, this.buf, frames);
+  }
+
+  // The note in the high byte and the fraction of a semitone in the low
+  pitch(note) {
+    const pitch = Math.round((note + this.bend) * 256);
+    return Math.min(Math.max(pitch, 0), 0xffff);
+  }
+
+  noteOn({ note, velocity }) {
+    this.osc.note_on(note, velocity);
+    // A bend, which the unit picks up on its next cycle. Each of several
+    // voices keeps the pitch it started at.
+    this.osc.set_pitch(this.pitch(note));
+    this.gain = velocity / 127;
+  }
+
+  noteOff(note) {
+    const i = this.held.findLastIndex((held) => held.note === note);
+    if (i < 0) {
+      return;
+    }
+    this.held.splice(i, 1);
+    const last = this.held.at(-1);
+    if (this.voices > 1) {
+      this.osc.note_off(note);
+    } else if (i === this.held.length) {
+      // Only releasing the note playing changes what a single voice plays
+      if (last) {
+        this.noteOn(last);
+      } else {
+        this.osc.note_off(note);
+      }
+    }
+    this.gain = last ? last.velocity / 127 : 0;
+  }
+
+  message(msg) {
+    switch (msg.type) {
+      case "on": {
+        // A note played again moves to the end, restarting its voice
+        const i = this.held.findLastIndex((held) => held.note === msg.note);
+        if (i >= 0) {
+          this.held.splice(i, 1);
+        }
+        this.held.push({ note: msg.note, velocity: msg.velocity });
+        this.noteOn(msg);
+        break;
+      }
+      case "off":
+        this.noteOff(msg.note);
+        break;
+      case "bend":
+        this.bend = msg.bend;
+        if (this.held.length > 0) {
+          this.osc.set_pitch(this.pitch(this.held.at(-1).note));
+        }
+        break;
+      case "param":
+        this.osc.param(msg.idx, msg.value);
+        break;
+      case "value":
+        this.osc.value(msg.value);
+        break;
+    }
+  }
+
+  process(inputs, outputs) {
+    const output = outputs[0];
+    const frames = output[0].length;
+    if (frames > this.frames) {
+      this.allocate(frames);
+    }
+
+    this.osc.cycle(this.buf, frames);
+    // Growing memory, which the unit shouldn't do but may during the cycle,
+    // detaches the old view
+    if (this.samples.buffer !== this.osc.memory.buffer) {
+      this.samples = new Float32Array(this.osc.memory.buffer, this.buf, this.frames);
+    }
+    for (const channel of output) {
+      for (let i = 0; i < frames; i++) {
+        channel[i] = this.samples[i] * this.gain;
+      }
+    }
+    return true;
+  }
+}
+
+registerProcessor("@MODULE_NAME@", OscProcessor);